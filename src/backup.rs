@@ -0,0 +1,486 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs;
+use std::os::unix::fs::chown;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::changeset::replace_file;
+use crate::config::BackupConfig;
+use crate::paths::{sudo_owner, xdg_state_home, APP_DIR_NAME};
+use crate::xfconf_dbus::{PropertyChange, XfconfBus};
+
+/// Name of the manifest file stored in every snapshot directory.
+const MANIFEST_FILE_NAME: &str = "manifest.toml";
+
+/// Name of the subdirectory holding the copied files of a snapshot.
+const FILES_DIR_NAME: &str = "files";
+
+/// Describes one file saved in a snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Absolute path of the original file.
+    pub path: PathBuf,
+    /// Name of the copy inside the snapshot's `files` directory.
+    pub file: String,
+    /// SHA-256 checksum of the original contents.
+    pub sha256: String,
+    /// SHA-256 checksum of the contents written by the tool, recorded after the update.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub written_sha256: Option<String>,
+    /// The file did not exist before the update; restoring removes it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub created: bool,
+}
+
+/// The `manifest.toml` of a snapshot, listing every saved file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Creation time in RFC 3339 format (UTC).
+    pub created: String,
+    pub files: Vec<ManifestEntry>,
+    /// Properties set through xfconfd instead of a file, with their previous values.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub xfconf: Vec<PropertyChange>,
+}
+
+/// A backup snapshot stored on disk.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// Snapshot identifier, which is also the name of its directory (e.g. `20261018T093000Z`).
+    pub id: String,
+    pub dir: PathBuf,
+    pub manifest: Manifest,
+}
+
+/// Returns the directory holding all snapshots
+/// (`$XDG_STATE_HOME/xfce4-transparent-whiskermenu/backups`).
+pub fn backups_dir() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(xdg_state_home()?.join(APP_DIR_NAME).join("backups"))
+}
+
+/// Computes the hex encoded SHA-256 checksum of the given bytes.
+pub fn sha256_hex(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Converts seconds since the Unix epoch to a UTC `(year, month, day, hour, minute, second)`.
+fn utc_from_unix(secs: u64) -> (i64, u32, u32, u32, u32, u32) {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (
+        year,
+        month,
+        day,
+        (rem / 3_600) as u32,
+        (rem % 3_600 / 60) as u32,
+        (rem % 60) as u32,
+    )
+}
+
+/// Hands a path created under `sudo` back to the invoking user.
+fn restore_owner(path: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some((uid, gid)) = sudo_owner() {
+        chown(path, Some(uid), Some(gid))?;
+    }
+    Ok(())
+}
+
+/// Creates the snapshot directory for the current time, making the name unique if needed.
+fn create_snapshot_dir(root: &Path) -> Result<(String, PathBuf, String), Box<dyn Error>> {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let (year, month, day, hour, minute, second) = utc_from_unix(secs);
+    let base_id = format!("{year:04}{month:02}{day:02}T{hour:02}{minute:02}{second:02}Z");
+    let created = format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z");
+
+    if !root.exists() {
+        fs::create_dir_all(root)?;
+        if let Some(app_dir) = root.parent() {
+            restore_owner(app_dir)?;
+        }
+        restore_owner(root)?;
+    }
+
+    let mut id = base_id.clone();
+    let mut counter = 1;
+    while root.join(&id).exists() {
+        id = format!("{base_id}-{counter}");
+        counter += 1;
+    }
+    let dir = root.join(&id);
+    fs::create_dir(&dir)?;
+    Ok((id, dir, created))
+}
+
+/// Returns the sort key of a snapshot id: its timestamp, then the counter that
+/// [`create_snapshot_dir`] appends within the same second, so `…Z-10` follows `…Z-2`.
+fn snapshot_order(id: &str) -> (&str, u64) {
+    match id.split_once('-') {
+        Some((timestamp, counter)) => (timestamp, counter.parse().unwrap_or(0)),
+        None => (id, 0),
+    }
+}
+
+/// Snapshots every file and xfconf property the run is about to modify.
+///
+/// Each file is copied into `backups/<timestamp>/files/` and listed in a `manifest.toml`
+/// together with its original path and checksum. Files that do not exist yet are recorded
+/// as created, so that restoring removes them. Properties set through xfconfd are listed
+/// with their previous values. Old snapshots are pruned by [`Snapshot::record_written`] once
+/// the update succeeded.
+///
+/// # Arguments
+/// - `targets`: Paths of the files that will be modified.
+/// - `properties`: The xfconf properties that will be set over D-Bus.
+/// - `settings`: The `[backup]` section of the configuration.
+///
+/// # Returns
+/// - An `Ok(Some(Snapshot))` describing the new snapshot.
+/// - An `Ok(None)` if backups are disabled or there is nothing to back up.
+/// - An `Err` if any file cannot be read or copied.
+pub fn create_snapshot(
+    targets: &[PathBuf],
+    properties: &[PropertyChange],
+    settings: &BackupConfig,
+) -> Result<Option<Snapshot>, Box<dyn Error>> {
+    if !settings.enabled || (targets.is_empty() && properties.is_empty()) {
+        return Ok(None);
+    }
+
+    let root = backups_dir()?;
+    let (id, dir, created) = create_snapshot_dir(&root)?;
+    let files_dir = dir.join(FILES_DIR_NAME);
+    fs::create_dir(&files_dir)?;
+
+    let mut files = Vec::new();
+    for (index, path) in targets.iter().enumerate() {
+        if !path.exists() {
+            files.push(ManifestEntry {
+                path: path.clone(),
+                file: String::new(),
+                sha256: String::new(),
+                written_sha256: None,
+                created: true,
+            });
+            continue;
+        }
+        let content =
+            fs::read(path).map_err(|e| format!("Failed to back up {}: {e}", path.display()))?;
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let file = format!("{index}-{name}");
+        fs::write(files_dir.join(&file), &content)?;
+        restore_owner(&files_dir.join(&file))?;
+
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+        files.push(ManifestEntry {
+            path,
+            file,
+            sha256: sha256_hex(&content),
+            written_sha256: None,
+            created: false,
+        });
+    }
+
+    let snapshot = Snapshot {
+        id,
+        dir,
+        manifest: Manifest {
+            created,
+            files,
+            xfconf: properties.to_vec(),
+        },
+    };
+    snapshot.write_manifest()?;
+    restore_owner(&files_dir)?;
+    restore_owner(&snapshot.dir)?;
+
+    Ok(Some(snapshot))
+}
+
+impl Snapshot {
+    fn write_manifest(&self) -> Result<(), Box<dyn Error>> {
+        let path = self.dir.join(MANIFEST_FILE_NAME);
+        fs::write(&path, toml::to_string(&self.manifest)?)?;
+        restore_owner(&path)
+    }
+
+    /// Removes a snapshot of an update that failed and changed nothing.
+    pub fn discard(self) -> Result<(), Box<dyn Error>> {
+        fs::remove_dir_all(&self.dir)?;
+        Ok(())
+    }
+
+    /// Records the checksum of every file as written by the update, then prunes old snapshots.
+    ///
+    /// `restore` compares against these checksums to detect files that were edited by hand
+    /// after the tool modified them. Pruning waits until here so that a failed update, whose
+    /// snapshot is discarded, never costs an older snapshot.
+    ///
+    /// # Arguments
+    /// - `keep`: How many snapshots to keep, including this one.
+    pub fn record_written(&mut self, keep: usize) -> Result<(), Box<dyn Error>> {
+        for entry in &mut self.manifest.files {
+            entry.written_sha256 = fs::read(&entry.path).ok().map(|c| sha256_hex(&c));
+        }
+        self.write_manifest()?;
+        match self.dir.parent() {
+            Some(root) => prune_snapshots(root, keep),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Lists all snapshots, oldest first.
+///
+/// Directories without a readable manifest are ignored.
+pub fn list_snapshots() -> Result<Vec<Snapshot>, Box<dyn Error>> {
+    let root = backups_dir()?;
+    if !root.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(&root)? {
+        let dir = entry?.path();
+        let Ok(content) = fs::read_to_string(dir.join(MANIFEST_FILE_NAME)) else {
+            continue;
+        };
+        let Ok(manifest) = toml::from_str::<Manifest>(&content) else {
+            continue;
+        };
+        let id = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        snapshots.push(Snapshot { id, dir, manifest });
+    }
+    snapshots.sort_by(|a, b| snapshot_order(&a.id).cmp(&snapshot_order(&b.id)));
+    Ok(snapshots)
+}
+
+/// Prints every snapshot with its creation time and saved files.
+pub fn print_snapshots() -> Result<(), Box<dyn Error>> {
+    let snapshots = list_snapshots()?;
+    if snapshots.is_empty() {
+        println!("No snapshots in {}", backups_dir()?.display());
+        return Ok(());
+    }
+    for snapshot in snapshots {
+        println!("{}  (created {})", snapshot.id, snapshot.manifest.created);
+        for entry in &snapshot.manifest.files {
+            println!("    {}", entry.path.display());
+        }
+        for change in &snapshot.manifest.xfconf {
+            println!("    xfconf {}{}", change.channel, change.property);
+        }
+    }
+    Ok(())
+}
+
+/// Outcome of restoring a single file.
+enum RestoreAction {
+    /// The file still holds its original contents.
+    Unchanged,
+    /// The file holds what the tool wrote and can be restored safely.
+    Restore,
+    /// The file no longer exists and will be recreated.
+    Missing,
+    /// The file was edited after the tool wrote it.
+    Modified,
+}
+
+fn restore_action(entry: &ManifestEntry) -> RestoreAction {
+    let Ok(current) = fs::read(&entry.path) else {
+        return if entry.created {
+            RestoreAction::Unchanged
+        } else {
+            RestoreAction::Missing
+        };
+    };
+    let current = sha256_hex(&current);
+    if current == entry.sha256 {
+        RestoreAction::Unchanged
+    } else if entry.written_sha256.as_deref() == Some(current.as_str()) {
+        RestoreAction::Restore
+    } else {
+        RestoreAction::Modified
+    }
+}
+
+/// Restores every file of a snapshot to its original contents.
+///
+/// Every backup copy is checked against its recorded checksum before any file is touched.
+/// Before overwriting, each target is compared with the checksum recorded after the tool
+/// wrote it. Files edited since then are reported and skipped unless `force` is set. Files
+/// are replaced through a temporary file, like [`Changeset::commit`] does.
+///
+/// [`Changeset::commit`]: crate::changeset::Changeset::commit
+///
+/// # Arguments
+/// - `id`: The snapshot identifier, or `None` for the most recent snapshot.
+/// - `force`: Overwrite files even if they were edited after the update.
+///
+/// # Returns
+/// - `Ok(())` once every restorable file was written back.
+/// - `Err(Box<dyn Error>)` if the snapshot does not exist, a backup copy is corrupt or a
+///   write fails.
+pub fn restore_snapshot(id: Option<&str>, force: bool) -> Result<(), Box<dyn Error>> {
+    let snapshots = list_snapshots()?;
+    let snapshot = match id {
+        Some(id) => snapshots
+            .iter()
+            .find(|s| s.id == id)
+            .ok_or_else(|| format!("Snapshot '{id}' not found (see `restore --list`)"))?,
+        None => snapshots.last().ok_or("No snapshots to restore")?,
+    };
+    let bus = if snapshot.manifest.xfconf.is_empty() {
+        None
+    } else {
+        Some(XfconfBus::connect().map_err(|e| {
+            format!(
+                "Snapshot {} holds xfconf properties, which are restored through xfconfd: {e}",
+                snapshot.id
+            )
+        })?)
+    };
+
+    // Check every backup copy before touching any file, so a corrupt snapshot changes nothing.
+    let mut backups = Vec::new();
+    for entry in &snapshot.manifest.files {
+        if entry.created {
+            backups.push(Vec::new());
+            continue;
+        }
+        let backup = fs::read(snapshot.dir.join(FILES_DIR_NAME).join(&entry.file))
+            .map_err(|e| format!("Backup copy of {} is missing: {e}", entry.path.display()))?;
+        if sha256_hex(&backup) != entry.sha256 {
+            return Err(format!(
+                "Backup copy of {} in snapshot {} is corrupt (checksum mismatch)",
+                entry.path.display(),
+                snapshot.id
+            )
+            .into());
+        }
+        backups.push(backup);
+    }
+
+    let mut skipped = 0;
+    for (entry, backup) in snapshot.manifest.files.iter().zip(&backups) {
+        match restore_action(entry) {
+            RestoreAction::Unchanged => {
+                println!("unchanged  {}", entry.path.display());
+                continue;
+            }
+            RestoreAction::Modified if !force => {
+                eprintln!(
+                    "warning: {} was edited after it was updated; skipping (use --force to overwrite)",
+                    entry.path.display()
+                );
+                skipped += 1;
+                continue;
+            }
+            RestoreAction::Modified => {
+                eprintln!(
+                    "warning: {} was edited after it was updated; overwriting",
+                    entry.path.display()
+                );
+            }
+            RestoreAction::Restore | RestoreAction::Missing => {}
+        }
+
+        if entry.created {
+            fs::remove_file(&entry.path)
+                .map_err(|e| format!("Failed to remove {}: {e}", entry.path.display()))?;
+            println!("removed    {}", entry.path.display());
+            continue;
+        }
+        replace_file(&entry.path, backup)
+            .map_err(|e| format!("Failed to restore {}: {e}", entry.path.display()))?;
+        println!("restored   {}", entry.path.display());
+    }
+    if let Some(bus) = &bus {
+        skipped += restore_properties(bus, &snapshot.manifest.xfconf, force)?;
+    }
+
+    if skipped > 0 {
+        println!(
+            "Restored snapshot {} with {skipped} file(s) skipped",
+            snapshot.id
+        );
+    } else {
+        println!("Restored snapshot {}", snapshot.id);
+    }
+    Ok(())
+}
+
+/// Puts back the previous values of properties set through xfconfd, newest first.
+///
+/// A property whose live value is neither the one written nor the previous one was changed
+/// after the update; it is skipped unless `force` is set.
+///
+/// # Returns
+/// - The number of properties skipped.
+/// - An `Err` if a property cannot be read or set.
+fn restore_properties(
+    bus: &XfconfBus,
+    changes: &[PropertyChange],
+    force: bool,
+) -> Result<usize, Box<dyn Error>> {
+    let mut skipped = 0;
+    for change in changes.iter().rev() {
+        let name = format!("{}{}", change.channel, change.property);
+        let current = bus.get_property(&change.channel, &change.property)?;
+        if current == change.previous {
+            println!("unchanged  xfconf {name}");
+            continue;
+        }
+        if current.as_ref() != Some(&change.value) {
+            if !force {
+                eprintln!(
+                    "warning: xfconf {name} was changed after it was updated; skipping (use --force to overwrite)"
+                );
+                skipped += 1;
+                continue;
+            }
+            eprintln!("warning: xfconf {name} was changed after it was updated; overwriting");
+        }
+        bus.restore(change)?;
+        println!("restored   xfconf {name}");
+    }
+    Ok(skipped)
+}
+
+/// Removes the oldest snapshots so that at most `keep` remain.
+fn prune_snapshots(root: &Path, keep: usize) -> Result<(), Box<dyn Error>> {
+    let mut ids: Vec<String> = fs::read_dir(root)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join(MANIFEST_FILE_NAME).is_file())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    ids.sort_by(|a, b| snapshot_order(a).cmp(&snapshot_order(b)));
+
+    let excess = ids.len().saturating_sub(keep.max(1));
+    for id in &ids[..excess] {
+        fs::remove_dir_all(root.join(id))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,299 @@
+use similar::TextDiff;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Write};
+use std::os::unix::fs::{chown, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::paths::sudo_owner;
+
+/// A file whose new contents have been computed but not yet written.
+#[derive(Debug)]
+struct PendingFile {
+    path: PathBuf,
+    original: String,
+    updated: String,
+    /// Whether the file existed before the update.
+    existed: bool,
+}
+
+/// Collects the new contents of every file an update touches, in memory.
+///
+/// The update functions read files through the changeset, so several updates to the same
+/// file (the theme CSS is touched up to three times) build on each other. Nothing is
+/// written to disk until [`Changeset::commit`] is called, which makes it possible to show
+/// a diff instead.
+#[derive(Debug, Default)]
+pub struct Changeset {
+    files: Vec<PendingFile>,
+}
+
+impl Changeset {
+    pub fn new() -> Self {
+        Changeset::default()
+    }
+
+    /// Returns the current contents of a file, including changes staged so far.
+    ///
+    /// # Returns
+    /// - An `Ok(String)` with the staged contents, or the contents on disk on first access.
+    /// - An `Err` if the file cannot be read.
+    pub fn read(&mut self, path: &Path) -> Result<String, Box<dyn Error>> {
+        if let Some(file) = self.files.iter().find(|f| f.path == path) {
+            return Ok(file.updated.clone());
+        }
+        let original = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        self.files.push(PendingFile {
+            path: path.to_path_buf(),
+            original: original.clone(),
+            updated: original.clone(),
+            existed: true,
+        });
+        Ok(original)
+    }
+
+    /// Like [`Changeset::read`], but treats a missing file as empty so it can be created.
+    pub fn read_or_empty(&mut self, path: &Path) -> Result<String, Box<dyn Error>> {
+        if path.exists() || self.files.iter().any(|f| f.path == path) {
+            return self.read(path);
+        }
+        self.files.push(PendingFile {
+            path: path.to_path_buf(),
+            original: String::new(),
+            updated: String::new(),
+            existed: false,
+        });
+        Ok(String::new())
+    }
+
+    /// Stages new contents for a file previously returned by [`Changeset::read`].
+    pub fn stage(&mut self, path: &Path, content: String) {
+        match self.files.iter_mut().find(|f| f.path == path) {
+            Some(file) => file.updated = content,
+            None => self.files.push(PendingFile {
+                path: path.to_path_buf(),
+                original: String::new(),
+                updated: content,
+                existed: false,
+            }),
+        }
+    }
+
+    /// Removes a staged file so [`Changeset::commit`] does not write it.
+    ///
+    /// # Returns
+    /// The original and the updated contents, or `None` if the file was not staged.
+    pub fn take(&mut self, path: &Path) -> Option<(String, String)> {
+        let index = self.files.iter().position(|f| f.path == path)?;
+        let file = self.files.remove(index);
+        Some((file.original, file.updated))
+    }
+
+    /// Lists the staged files whose contents differ from the original.
+    pub fn changed_paths(&self) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .filter(|f| f.original != f.updated)
+            .map(|f| f.path.clone())
+            .collect()
+    }
+
+    /// Returns `true` if any staged file differs from its original contents.
+    pub fn has_changes(&self) -> bool {
+        self.files.iter().any(|f| f.original != f.updated)
+    }
+
+    /// Prints a unified diff for every changed file.
+    ///
+    /// Lines are colored when standard output is a terminal.
+    pub fn print_diff(&self) {
+        let color = io::stdout().is_terminal();
+
+        for file in self.files.iter().filter(|f| f.original != f.updated) {
+            let path = file.path.display().to_string();
+            let path = path.trim_start_matches('/');
+            let old_path = if file.existed {
+                format!("a/{path}")
+            } else {
+                "/dev/null".to_string()
+            };
+            let diff = TextDiff::from_lines(&file.original, &file.updated);
+            let unified = diff
+                .unified_diff()
+                .context_radius(3)
+                .header(&old_path, &format!("b/{path}"))
+                .to_string();
+
+            for line in unified.lines() {
+                if !color {
+                    println!("{line}");
+                    continue;
+                }
+                let code = if line.starts_with("+++") || line.starts_with("---") {
+                    "1"
+                } else if line.starts_with('+') {
+                    "32"
+                } else if line.starts_with('-') {
+                    "31"
+                } else if line.starts_with("@@") {
+                    "36"
+                } else {
+                    ""
+                };
+                if code.is_empty() {
+                    println!("{line}");
+                } else {
+                    println!("\x1b[{code}m{line}\x1b[0m");
+                }
+            }
+        }
+    }
+
+    /// Writes every changed file to disk as a single all-or-nothing transaction.
+    ///
+    /// The new contents of all files are first written to temporary files next to their
+    /// targets, with the original mode and ownership. Only when every temporary file is in
+    /// place are they renamed over the targets. If a rename fails, the files already
+    /// replaced are restored to their original contents, and files that did not exist
+    /// before are removed again.
+    ///
+    /// # Returns
+    /// - `Ok(())` once all files are written.
+    /// - `Err(Box<dyn Error>)` if any step fails; no target is left modified in that case.
+    pub fn commit(&self) -> Result<(), Box<dyn Error>> {
+        let changed: Vec<&PendingFile> = self
+            .files
+            .iter()
+            .filter(|f| f.original != f.updated)
+            .collect();
+
+        let mut prepared: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for file in &changed {
+            let target = fs::canonicalize(&file.path).unwrap_or_else(|_| file.path.clone());
+            let prepared_file =
+                create_parent(&target).and_then(|()| write_temp(&target, file.updated.as_bytes()));
+            match prepared_file {
+                Ok(temp) => prepared.push((temp, target)),
+                Err(e) => {
+                    for (temp, _) in &prepared {
+                        let _ = fs::remove_file(temp);
+                    }
+                    return Err(format!("Failed to write {}: {e}", file.path.display()).into());
+                }
+            }
+        }
+
+        for (index, (temp, target)) in prepared.iter().enumerate() {
+            if let Err(e) = fs::rename(temp, target) {
+                for (temp, _) in &prepared[index..] {
+                    let _ = fs::remove_file(temp);
+                }
+                let rollback_errors = rollback(&changed[..index], &prepared[..index]);
+                let mut message = format!("Failed to replace {}: {e}", target.display());
+                if rollback_errors.is_empty() {
+                    message.push_str("; all files were rolled back");
+                } else {
+                    message.push_str(&format!(
+                        "; rollback failed for: {}",
+                        rollback_errors.join(", ")
+                    ));
+                }
+                return Err(message.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Creates the parent directory of a new file, handing it to the invoking user under `sudo`.
+fn create_parent(target: &Path) -> Result<(), Box<dyn Error>> {
+    let Some(parent) = target.parent() else {
+        return Ok(());
+    };
+    if !parent.exists() {
+        fs::create_dir_all(parent)?;
+        if let Some((uid, gid)) = sudo_owner() {
+            chown(parent, Some(uid), Some(gid))?;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces one file through a temporary file next to it, so it is never left half written.
+///
+/// The mode and ownership of an existing file are kept, and missing parent directories are
+/// created.
+///
+/// # Errors
+/// Returns an error if the temporary file cannot be written or renamed over `target`.
+pub fn replace_file(target: &Path, content: &[u8]) -> Result<(), Box<dyn Error>> {
+    let target = fs::canonicalize(target).unwrap_or_else(|_| target.to_path_buf());
+    create_parent(&target)?;
+    let temp = write_temp(&target, content)?;
+    fs::rename(&temp, &target).map_err(|e| {
+        let _ = fs::remove_file(&temp);
+        e.into()
+    })
+}
+
+/// Writes `content` to a temporary file next to `target`, copying the target's mode and
+/// ownership. A new file is given to the invoking user when running under `sudo`.
+///
+/// # Returns
+/// - An `Ok(PathBuf)` with the path of the temporary file.
+/// - An `Err` if the file cannot be created, written or given the original permissions.
+fn write_temp(target: &Path, content: &[u8]) -> Result<PathBuf, Box<dyn Error>> {
+    let dir = target.parent().ok_or("Target has no parent directory")?;
+    let name = target
+        .file_name()
+        .ok_or("Target has no file name")?
+        .to_string_lossy();
+    let temp = dir.join(format!(".{name}.{}.tmp", process::id()));
+
+    let result = (|| -> Result<(), Box<dyn Error>> {
+        let mut file = File::create(&temp)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+
+        if let Ok(metadata) = fs::metadata(target) {
+            fs::set_permissions(&temp, metadata.permissions())?;
+            let temp_metadata = fs::metadata(&temp)?;
+            if temp_metadata.uid() != metadata.uid() || temp_metadata.gid() != metadata.gid() {
+                chown(&temp, Some(metadata.uid()), Some(metadata.gid()))?;
+            }
+        } else if let Some((uid, gid)) = sudo_owner() {
+            chown(&temp, Some(uid), Some(gid))?;
+        }
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => Ok(temp),
+        Err(e) => {
+            let _ = fs::remove_file(&temp);
+            Err(e)
+        }
+    }
+}
+
+/// Restores the original contents of files that were already replaced.
+///
+/// # Returns
+/// - The paths of the files that could not be restored.
+fn rollback(files: &[&PendingFile], replaced: &[(PathBuf, PathBuf)]) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (file, (_, target)) in files.iter().zip(replaced) {
+        if !file.existed {
+            if fs::remove_file(target).is_err() {
+                errors.push(target.display().to_string());
+            }
+            continue;
+        }
+        if replace_file(target, file.original.as_bytes()).is_err() {
+            errors.push(target.display().to_string());
+        }
+    }
+    errors
+}
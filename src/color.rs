@@ -0,0 +1,641 @@
+use std::error::Error;
+use std::fmt;
+
+/// An sRGB color with alpha, every channel in `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+    pub alpha: f64,
+}
+
+impl Color {
+    /// Parses a color literal.
+    ///
+    /// Accepted forms:
+    /// - `#RGB`, `#RGBA`, `#RRGGBB` and `#RRGGBBAA`
+    /// - `rgb()`/`rgba()` with numbers (0–255) or percentages, comma or space separated,
+    ///   with an optional alpha (`rgb(1 2 3 / 50%)`)
+    /// - `hsl()`/`hsla()` with a hue in degrees (`deg`, `turn`, `rad` units allowed)
+    /// - every CSS named color, including `transparent`
+    ///
+    /// Names, `@define-color` references and functions such as `mix()` are handled by
+    /// [`ColorScope`](crate::color_expr::ColorScope).
+    ///
+    /// # Errors
+    /// Returns an error describing why the value is not a color.
+    pub fn parse(value: &str) -> Result<Color, Box<dyn Error>> {
+        let value = value.trim();
+        if let Some(hex) = value.strip_prefix('#') {
+            return Color::from_hex(hex).ok_or_else(|| {
+                format!(
+                    "Invalid color '{value}'. Hex colors use #RGB, #RGBA, #RRGGBB or #RRGGBBAA."
+                )
+                .into()
+            });
+        }
+        if let Some((function, arguments)) = split_function(value) {
+            return match function.to_ascii_lowercase().as_str() {
+                "rgb" | "rgba" => Color::from_rgb_arguments(arguments),
+                "hsl" | "hsla" => Color::from_hsl_arguments(arguments),
+                _ => Err(format!("Unknown color function '{function}()'").into()),
+            }
+            .map_err(|e| format!("Invalid color '{value}': {e}").into());
+        }
+        named_color(value).ok_or_else(|| format!("Unknown color '{value}'").into())
+    }
+
+    /// Creates a color from 8-bit channels and an alpha in `0.0..=1.0`.
+    pub fn from_rgb8(red: u8, green: u8, blue: u8, alpha: f64) -> Color {
+        Color {
+            red: f64::from(red) / 255.0,
+            green: f64::from(green) / 255.0,
+            blue: f64::from(blue) / 255.0,
+            alpha,
+        }
+    }
+
+    fn from_hex(hex: &str) -> Option<Color> {
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digits: Vec<u8> = match hex.len() {
+            3 | 4 => hex
+                .chars()
+                .map(|c| u8::from_str_radix(&format!("{c}{c}"), 16).ok())
+                .collect::<Option<_>>()?,
+            6 | 8 => (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+                .collect::<Option<_>>()?,
+            _ => return None,
+        };
+        let alpha = digits.get(3).map_or(1.0, |a| f64::from(*a) / 255.0);
+        Some(Color::from_rgb8(digits[0], digits[1], digits[2], alpha))
+    }
+
+    fn from_rgb_arguments(arguments: &str) -> Result<Color, Box<dyn Error>> {
+        let parts = split_arguments(arguments)?;
+        Ok(Color {
+            red: parse_channel(parts[0])?,
+            green: parse_channel(parts[1])?,
+            blue: parse_channel(parts[2])?,
+            alpha: parts
+                .get(3)
+                .map_or(Ok(1.0), |alpha| parse_fraction(alpha))?,
+        })
+    }
+
+    fn from_hsl_arguments(arguments: &str) -> Result<Color, Box<dyn Error>> {
+        let parts = split_arguments(arguments)?;
+        Ok(Color::from_hsl(
+            parse_hue(parts[0])?,
+            parse_percentage(parts[1])?,
+            parse_percentage(parts[2])?,
+            parts
+                .get(3)
+                .map_or(Ok(1.0), |alpha| parse_fraction(alpha))?,
+        ))
+    }
+
+    /// Creates a color from a hue in degrees and saturation and lightness in `0.0..=1.0`.
+    pub fn from_hsl(hue: f64, saturation: f64, lightness: f64, alpha: f64) -> Color {
+        let hue = hue.rem_euclid(360.0) / 360.0;
+        let q = if lightness < 0.5 {
+            lightness * (1.0 + saturation)
+        } else {
+            lightness + saturation - lightness * saturation
+        };
+        let p = 2.0 * lightness - q;
+        let channel = |t: f64| {
+            let t = t.rem_euclid(1.0);
+            if t < 1.0 / 6.0 {
+                p + (q - p) * 6.0 * t
+            } else if t < 0.5 {
+                q
+            } else if t < 2.0 / 3.0 {
+                p + (q - p) * (2.0 / 3.0 - t) * 6.0
+            } else {
+                p
+            }
+        };
+        Color {
+            red: channel(hue + 1.0 / 3.0),
+            green: channel(hue),
+            blue: channel(hue - 1.0 / 3.0),
+            alpha,
+        }
+    }
+
+    /// Returns the hue in degrees and the saturation and lightness in `0.0..=1.0`.
+    pub fn to_hsl(self) -> (f64, f64, f64) {
+        let max = self.red.max(self.green).max(self.blue);
+        let min = self.red.min(self.green).min(self.blue);
+        let lightness = (max + min) / 2.0;
+        let delta = max - min;
+        if delta == 0.0 {
+            return (0.0, 0.0, lightness);
+        }
+        let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+        let hue = if max == self.red {
+            ((self.green - self.blue) / delta).rem_euclid(6.0)
+        } else if max == self.green {
+            (self.blue - self.red) / delta + 2.0
+        } else {
+            (self.red - self.green) / delta + 4.0
+        };
+        (hue * 60.0, saturation.clamp(0.0, 1.0), lightness)
+    }
+
+    /// Returns the color with `amount` added to its HSL lightness; negative amounts darken.
+    pub fn lighten(self, amount: f64) -> Color {
+        let (hue, saturation, lightness) = self.to_hsl();
+        Color::from_hsl(
+            hue,
+            saturation,
+            (lightness + amount).clamp(0.0, 1.0),
+            self.alpha,
+        )
+    }
+
+    /// Returns the color with its lightness and saturation multiplied by `factor`, like
+    /// GTK's `shade()`.
+    pub fn shade(self, factor: f64) -> Color {
+        let (hue, saturation, lightness) = self.to_hsl();
+        Color::from_hsl(
+            hue,
+            (saturation * factor).clamp(0.0, 1.0),
+            (lightness * factor).clamp(0.0, 1.0),
+            self.alpha,
+        )
+    }
+
+    /// Mixes two colors channel by channel, alpha included, like GTK's `mix()`.
+    ///
+    /// `weight` is the share of `other`: `0.0` returns `self`, `1.0` returns `other`.
+    pub fn mix(self, other: Color, weight: f64) -> Color {
+        let blend = |a: f64, b: f64| a + (b - a) * weight;
+        Color {
+            red: blend(self.red, other.red),
+            green: blend(self.green, other.green),
+            blue: blend(self.blue, other.blue),
+            alpha: blend(self.alpha, other.alpha),
+        }
+    }
+
+    /// Composites the color over `backdrop` with the "source over" operator.
+    pub fn over(self, backdrop: Color) -> Color {
+        let alpha = self.alpha + backdrop.alpha * (1.0 - self.alpha);
+        if alpha == 0.0 {
+            return Color::from_rgb8(0, 0, 0, 0.0);
+        }
+        let blend = |top: f64, bottom: f64| {
+            (top * self.alpha + bottom * backdrop.alpha * (1.0 - self.alpha)) / alpha
+        };
+        Color {
+            red: blend(self.red, backdrop.red),
+            green: blend(self.green, backdrop.green),
+            blue: blend(self.blue, backdrop.blue),
+            alpha,
+        }
+    }
+
+    /// Returns the WCAG relative luminance of the color, ignoring its alpha.
+    pub fn relative_luminance(self) -> f64 {
+        let linear = |channel: f64| {
+            if channel <= 0.04045 {
+                channel / 12.92
+            } else {
+                ((channel + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(self.red) + 0.7152 * linear(self.green) + 0.0722 * linear(self.blue)
+    }
+
+    /// Returns the WCAG contrast ratio between two opaque colors, from 1 to 21.
+    pub fn contrast_ratio(self, other: Color) -> f64 {
+        let (a, b) = (self.relative_luminance(), other.relative_luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// Returns the color with its alpha replaced.
+    pub fn with_alpha(self, alpha: f64) -> Color {
+        Color { alpha, ..self }
+    }
+
+    /// Returns the color with its alpha multiplied by `opacity`.
+    ///
+    /// The configured opacities apply on top of any alpha the color itself carries.
+    pub fn with_opacity(self, opacity: f64) -> Color {
+        self.with_alpha(self.alpha * opacity)
+    }
+
+    /// Returns the channels as 8-bit values.
+    pub fn to_rgb8(self) -> [u8; 3] {
+        [self.red, self.green, self.blue]
+            .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+
+    /// Serializes the color for CSS: `#rrggbb` when opaque, `rgba(r, g, b, a)` otherwise.
+    pub fn to_css(self) -> String {
+        let [red, green, blue] = self.to_rgb8();
+        if self.alpha >= 1.0 {
+            format!("#{red:02x}{green:02x}{blue:02x}")
+        } else {
+            let alpha = (self.alpha.clamp(0.0, 1.0) * 1000.0).round() / 1000.0;
+            format!("rgba({red}, {green}, {blue}, {alpha})")
+        }
+    }
+
+    /// Returns the normalized `[red, green, blue, alpha]` doubles xfconf stores, e.g. in
+    /// the `background-rgba` array of a panel, rounded to six decimals.
+    pub fn to_xfconf(self) -> [f64; 4] {
+        [self.red, self.green, self.blue, self.alpha]
+            .map(|channel| (channel.clamp(0.0, 1.0) * 1e6).round() / 1e6)
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_css())
+    }
+}
+
+/// Splits `name(arguments)` into its parts.
+pub fn split_function(value: &str) -> Option<(&str, &str)> {
+    let open = value.find('(')?;
+    let arguments = value[open + 1..].strip_suffix(')')?;
+    let name = value[..open].trim();
+    (!name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-'))
+        .then_some((name, arguments))
+}
+
+/// Splits the arguments of `rgb()`/`hsl()` into three channels and an optional alpha.
+///
+/// Both the legacy comma syntax and the space syntax with `/ alpha` are accepted.
+///
+/// # Returns
+/// - An `Ok(Vec)` with three or four values, the alpha last.
+/// - An `Err` if there are fewer or more values.
+fn split_arguments(arguments: &str) -> Result<Vec<&str>, Box<dyn Error>> {
+    let parts: Vec<&str> = if arguments.contains(',') {
+        arguments.split(',').map(str::trim).collect()
+    } else {
+        let (channels, alpha) = match arguments.split_once('/') {
+            Some((channels, alpha)) => (channels, Some(alpha.trim())),
+            None => (arguments, None),
+        };
+        let mut parts: Vec<&str> = channels.split_whitespace().collect();
+        parts.extend(alpha);
+        parts
+    };
+    match parts.len() {
+        3 | 4 => Ok(parts),
+        count => Err(format!("expected 3 or 4 values, found {count}").into()),
+    }
+}
+
+/// Parses a finite number.
+pub fn parse_number(value: &str) -> Result<f64, Box<dyn Error>> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .ok_or_else(|| format!("'{value}' is not a number").into())
+}
+
+/// Parses an RGB channel, `0`–`255` or a percentage, to `0.0..=1.0`.
+fn parse_channel(value: &str) -> Result<f64, Box<dyn Error>> {
+    let channel = match value.strip_suffix('%') {
+        Some(percentage) => parse_number(percentage)? / 100.0,
+        None => parse_number(value)? / 255.0,
+    };
+    Ok(channel.clamp(0.0, 1.0))
+}
+
+/// Parses a percentage to `0.0..=1.0`.
+fn parse_percentage(value: &str) -> Result<f64, Box<dyn Error>> {
+    let number = value
+        .strip_suffix('%')
+        .ok_or_else(|| format!("'{value}' is not a percentage"))?;
+    Ok((parse_number(number)? / 100.0).clamp(0.0, 1.0))
+}
+
+/// Parses a fraction such as an alpha value, `0`–`1` or a percentage.
+pub fn parse_fraction(value: &str) -> Result<f64, Box<dyn Error>> {
+    let alpha = match value.strip_suffix('%') {
+        Some(percentage) => parse_number(percentage)? / 100.0,
+        None => parse_number(value)?,
+    };
+    Ok(alpha.clamp(0.0, 1.0))
+}
+
+/// Parses a hue to degrees.
+fn parse_hue(value: &str) -> Result<f64, Box<dyn Error>> {
+    let value = value.trim();
+    if let Some(degrees) = value.strip_suffix("deg") {
+        parse_number(degrees)
+    } else if let Some(turns) = value.strip_suffix("turn") {
+        Ok(parse_number(turns)? * 360.0)
+    } else if let Some(radians) = value.strip_suffix("rad") {
+        Ok(parse_number(radians)?.to_degrees())
+    } else {
+        parse_number(value)
+    }
+}
+
+/// Looks up a CSS named color, ignoring case.
+fn named_color(name: &str) -> Option<Color> {
+    let name = name.to_ascii_lowercase();
+    if name == "transparent" {
+        return Some(Color::from_rgb8(0, 0, 0, 0.0));
+    }
+    NAMED_COLORS
+        .iter()
+        .find(|(named, _)| *named == name)
+        .map(|(_, rgb)| {
+            let [red, green, blue] = rgb.to_be_bytes()[1..] else {
+                unreachable!("a u32 has four bytes")
+            };
+            Color::from_rgb8(red, green, blue, 1.0)
+        })
+}
+
+/// The CSS Color Module Level 4 named colors.
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn css(value: &str) -> String {
+        Color::parse(value).unwrap().to_css()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-3, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(css("#abc"), "#aabbcc");
+        assert_eq!(css("#abcd"), "rgba(170, 187, 204, 0.867)");
+        assert_eq!(css("#102030"), "#102030");
+        assert_eq!(css(" #10203080 "), "rgba(16, 32, 48, 0.502)");
+        assert_eq!(css("#FFFFFF"), "#ffffff");
+        for invalid in ["#12", "#12345", "#ggg", "#1234567890", "#"] {
+            assert!(Color::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn parses_rgb_functions() {
+        assert_eq!(css("rgb(16, 32, 48)"), "#102030");
+        assert_eq!(css("rgba(255, 0, 0, 0.5)"), "rgba(255, 0, 0, 0.5)");
+        assert_eq!(css("rgb(100% 0% 50% / 25%)"), "rgba(255, 0, 128, 0.25)");
+        assert_eq!(css("RGB(300, -5, 0)"), "#ff0000");
+        assert!(Color::parse("rgb(1, 2)").is_err());
+        assert!(Color::parse("rgb(1, 2, x)").is_err());
+        assert!(Color::parse("cmyk(0, 0, 0, 0)").is_err());
+    }
+
+    #[test]
+    fn parses_hsl_functions() {
+        assert_eq!(css("hsl(0, 100%, 50%)"), "#ff0000");
+        assert_eq!(css("hsl(120deg 100% 25%)"), "#008000");
+        assert_eq!(
+            css("hsla(0.5turn, 100%, 50%, 0.25)"),
+            "rgba(0, 255, 255, 0.25)"
+        );
+        assert_eq!(css("hsl(240, 0%, 50%)"), "#808080");
+        assert!(Color::parse("hsl(0, 100, 50%)").is_err());
+    }
+
+    #[test]
+    fn parses_named_colors() {
+        assert_eq!(css("rebeccapurple"), "#663399");
+        assert_eq!(css("White"), "#ffffff");
+        assert_eq!(css("transparent"), "rgba(0, 0, 0, 0)");
+        assert!(Color::parse("notacolor").is_err());
+    }
+
+    #[test]
+    fn to_css_round_trips() {
+        for value in [
+            "#102030",
+            "rgba(16, 32, 48, 0.5)",
+            "rgba(255, 255, 255, 0)",
+            "#00ff7f",
+        ] {
+            assert_eq!(css(value), value);
+            assert_eq!(css(&css(value)), value);
+        }
+        let color = Color::parse("hsl(200, 40%, 30%)").unwrap();
+        assert_eq!(
+            Color::parse(&color.to_css()).unwrap().to_rgb8(),
+            color.to_rgb8()
+        );
+    }
+
+    #[test]
+    fn applies_opacity_on_top_of_alpha() {
+        let color = Color::parse("rgba(0, 0, 0, 0.5)").unwrap();
+        assert_close(color.with_opacity(0.5).alpha, 0.25);
+        assert_close(color.with_alpha(0.8).alpha, 0.8);
+    }
+
+    #[test]
+    fn composites_over_a_backdrop() {
+        let white = Color::parse("white").unwrap();
+        let half_black = Color::parse("black").unwrap().with_opacity(0.5);
+        assert_eq!(half_black.over(white).to_css(), "#808080");
+        assert_eq!(white.over(half_black), white);
+        assert_eq!(Color::parse("transparent").unwrap().over(white), white);
+        let layered = half_black.over(half_black);
+        assert_close(layered.alpha, 0.75);
+        assert_eq!(layered.with_alpha(1.0).to_css(), "#000000");
+        assert_eq!(
+            Color::parse("transparent")
+                .unwrap()
+                .over(Color::parse("transparent").unwrap())
+                .alpha,
+            0.0
+        );
+    }
+
+    #[test]
+    fn computes_wcag_contrast() {
+        let black = Color::parse("black").unwrap();
+        let white = Color::parse("white").unwrap();
+        assert_close(black.contrast_ratio(white), 21.0);
+        assert_close(white.contrast_ratio(black), 21.0);
+        assert_close(white.contrast_ratio(white), 1.0);
+        // #777777 on white is the classic "just below AA" example.
+        let grey = Color::parse("#777").unwrap();
+        assert_close((grey.contrast_ratio(white) * 100.0).round() / 100.0, 4.48);
+    }
+
+    #[test]
+    fn adjusts_lightness_and_mixes() {
+        let base = Color::parse("hsl(0, 100%, 40%)").unwrap();
+        assert_eq!(base.lighten(0.1).to_css(), css("hsl(0, 100%, 50%)"));
+        assert_eq!(base.lighten(-1.0).to_css(), "#000000");
+        assert_eq!(base.shade(1.25).to_css(), css("hsl(0, 100%, 50%)"));
+        let black = Color::parse("black").unwrap();
+        let white = Color::parse("white").unwrap();
+        assert_eq!(black.mix(white, 0.0), black);
+        assert_eq!(black.mix(white, 1.0), white);
+        assert_eq!(black.mix(white, 0.5).to_css(), "#808080");
+    }
+}
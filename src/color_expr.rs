@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use crate::color::{parse_fraction, parse_number, split_function, Color};
+
+/// How deep names, `@name` references and nested functions may go before the expression
+/// is reported as a cycle.
+const MAX_DEPTH: usize = 16;
+
+/// GTK `@define-color` definitions a color may refer to with `@name`, mapped to their
+/// unparsed values.
+pub type ColorDefinitions = BTreeMap<String, String>;
+
+/// The names a color expression can refer to.
+///
+/// An expression is a color literal (see [`Color::parse`]), a name, an `@name` reference or
+/// one of these functions applied to expressions:
+/// - `lighten(color, amount)` / `darken(color, amount)`: add to or subtract from the HSL
+///   lightness, e.g. `lighten(base, 8%)`
+/// - `mix(color, other, weight)`: blend towards `other` by `weight`, e.g. `mix(base, accent, 30%)`
+/// - `alpha(color, factor)`: multiply the alpha, e.g. `alpha(accent, 0.4)`
+/// - `shade(color, factor)`: GTK's `shade()`, as used in theme `@define-color` rules
+///
+/// Amounts, weights and factors are numbers from 0 to 1 or percentages.
+pub struct ColorScope<'a> {
+    /// Colors usable by bare name (`base`, `accent`, ...), mapped to their expressions.
+    pub names: BTreeMap<&'a str, &'a str>,
+    /// Definitions usable as `@name`.
+    pub definitions: &'a ColorDefinitions,
+}
+
+impl ColorScope<'_> {
+    /// Evaluates a color expression.
+    ///
+    /// # Errors
+    /// Returns an error if the expression is malformed, refers to an unknown name or
+    /// definition, or refers to itself.
+    pub fn evaluate(&self, expression: &str) -> Result<Color, Box<dyn Error>> {
+        self.evaluate_nested(expression, 0)
+    }
+
+    fn evaluate_nested(&self, expression: &str, depth: usize) -> Result<Color, Box<dyn Error>> {
+        if depth > MAX_DEPTH {
+            return Err("colors refer to each other too deeply (is there a cycle?)".into());
+        }
+        let expression = expression.trim();
+        // Only the outermost step names what failed, not every link of a chain.
+        let context = |result: Result<Color, Box<dyn Error>>, label: &str| {
+            if depth == 0 {
+                result.map_err(|e| format!("{label}: {e}").into())
+            } else {
+                result
+            }
+        };
+
+        if let Some(name) = expression.strip_prefix('@') {
+            let definition = self
+                .definitions
+                .get(name)
+                .ok_or_else(|| format!("@{name} is not defined with @define-color in the theme"))?;
+            return context(self.evaluate_nested(definition, depth + 1), expression);
+        }
+        if let Some(value) = self.names.get(expression) {
+            return context(self.evaluate_nested(value, depth + 1), expression);
+        }
+        let Some((function, arguments)) = split_function(expression) else {
+            return Color::parse(expression);
+        };
+
+        let function = function.to_ascii_lowercase();
+        let arguments = split_arguments(arguments);
+        let color = |argument: &str| self.evaluate_nested(argument, depth + 1);
+        let result = match (function.as_str(), &arguments[..]) {
+            ("lighten", [base, amount]) => {
+                color(base).and_then(|c| Ok(c.lighten(parse_fraction(amount)?)))
+            }
+            ("darken", [base, amount]) => {
+                color(base).and_then(|c| Ok(c.lighten(-parse_fraction(amount)?)))
+            }
+            ("mix", [base, other, weight]) => {
+                color(base).and_then(|c| Ok(c.mix(color(other)?, parse_fraction(weight)?)))
+            }
+            ("alpha", [base, factor]) => {
+                color(base).and_then(|c| Ok(c.with_opacity(parse_fraction(factor)?)))
+            }
+            ("shade", [base, factor]) => {
+                color(base).and_then(|c| Ok(c.shade(parse_number(factor)?)))
+            }
+            ("lighten" | "darken" | "alpha" | "shade", _) => {
+                Err(format!("{function}() takes a color and an amount").into())
+            }
+            ("mix", _) => Err("mix() takes two colors and a weight".into()),
+            // rgb(), hsl() and unknown functions
+            _ => return Color::parse(expression),
+        };
+        context(result, &format!("Invalid color '{expression}'"))
+    }
+}
+
+/// Splits function arguments on the commas outside nested parentheses.
+fn split_arguments(arguments: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (index, byte) in arguments.bytes().enumerate() {
+        match byte {
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            b',' if depth == 0 => {
+                parts.push(arguments[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(arguments[start..].trim());
+    parts
+}
+
+/// Checks that `name` can be used as a color name in expressions.
+///
+/// Names start with a letter and contain letters, digits, `_` and `-`.
+pub fn is_color_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(names: &[(&str, &str)], definitions: &[(&str, &str)], expression: &str) -> String {
+        try_evaluate(names, definitions, expression)
+            .unwrap()
+            .to_css()
+    }
+
+    fn try_evaluate(
+        names: &[(&str, &str)],
+        definitions: &[(&str, &str)],
+        expression: &str,
+    ) -> Result<Color, Box<dyn Error>> {
+        let definitions: ColorDefinitions = definitions
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let scope = ColorScope {
+            names: names.iter().copied().collect(),
+            definitions: &definitions,
+        };
+        scope.evaluate(expression)
+    }
+
+    #[test]
+    fn evaluates_literals() {
+        assert_eq!(evaluate(&[], &[], " #102030 "), "#102030");
+        assert_eq!(evaluate(&[], &[], "rgb(16, 32, 48)"), "#102030");
+        assert_eq!(evaluate(&[], &[], "red"), "#ff0000");
+    }
+
+    #[test]
+    fn applies_functions() {
+        let names = [("base", "hsl(0, 100%, 40%)"), ("accent", "#0000ff")];
+        assert_eq!(evaluate(&names, &[], "lighten(base, 10%)"), "#ff0000");
+        assert_eq!(evaluate(&names, &[], "darken(base, 0.4)"), "#000000");
+        assert_eq!(evaluate(&names, &[], "shade(base, 1.25)"), "#ff0000");
+        assert_eq!(
+            evaluate(&[("a", "black"), ("b", "white")], &[], "mix(a, b, 50%)"),
+            "#808080"
+        );
+        assert_eq!(
+            evaluate(&names, &[], "alpha(accent, 0.4)"),
+            "rgba(0, 0, 255, 0.4)"
+        );
+        assert_eq!(
+            evaluate(&names, &[], "MIX(lighten(base, 10%), accent, 0)"),
+            "#ff0000"
+        );
+    }
+
+    #[test]
+    fn resolves_names_and_definitions() {
+        let names = [("base", "@bg_color"), ("search", "lighten(base, 0%)")];
+        let definitions = [("bg_color", "shade(@dark, 1)"), ("dark", "#202020")];
+        assert_eq!(evaluate(&names, &definitions, "search"), "#202020");
+        assert_eq!(evaluate(&names, &definitions, "@bg_color"), "#202020");
+    }
+
+    #[test]
+    fn reports_unknown_names_and_bad_arguments() {
+        let error = |expression: &str| {
+            try_evaluate(&[("base", "#000")], &[], expression)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(error("accent").contains("Unknown color 'accent'"));
+        assert!(error("@theme_bg").contains("@theme_bg is not defined"));
+        assert!(error("lighten(base)").contains("takes a color and an amount"));
+        assert!(error("mix(base, base)").contains("mix() takes two colors"));
+        assert!(error("lighten(base, lots)").contains("not a number"));
+        assert!(error("lighten(nope, 10%)").starts_with("Invalid color 'lighten(nope, 10%)'"));
+    }
+
+    #[test]
+    fn reports_cycles_instead_of_recursing_forever() {
+        let cycle = |names: &[(&str, &str)], definitions: &[(&str, &str)], expression: &str| {
+            try_evaluate(names, definitions, expression)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(cycle(&[("base", "base")], &[], "base").contains("cycle"));
+        assert!(cycle(
+            &[
+                ("base", "lighten(accent, 5%)"),
+                ("accent", "mix(base, white, 10%)")
+            ],
+            &[],
+            "base"
+        )
+        .contains("cycle"));
+        assert!(cycle(&[], &[("a", "@b"), ("b", "shade(@a, 1.1)")], "@a").contains("cycle"));
+    }
+
+    #[test]
+    fn checks_color_names() {
+        assert!(is_color_name("accent"));
+        assert!(is_color_name("menu_bg-2"));
+        assert!(!is_color_name("2nd"));
+        assert!(!is_color_name("@accent"));
+        assert!(!is_color_name(""));
+    }
+}
@@ -1,168 +1,549 @@
 use regex::Regex;
 use std::error::Error;
+use std::fmt;
 use std::fs;
+use std::path::{Path, PathBuf};
 
-use crate::config;
-use crate::regex_patterns;
-use crate::utils::{hex_to_normalized_rgba, hex_to_rgba};
+use crate::changeset::Changeset;
+use crate::color::Color;
+use crate::config::{Backend, Config};
+use crate::css::Stylesheet;
+use crate::panel_layout::numbered;
+use crate::profiles::{ProfileTarget, ThemeProfile};
+use crate::user_css;
+use crate::whisker_rc::RcFile;
+use crate::xfconf::{Channel, Property, XfconfValue};
 
-/// Updates the Whisker Menu color and transparency in the linux theme configuration.
-///
-/// This function:
-/// 1. Reads the current theme configuration
-/// 2. Updates base menu colors and opacity using regular expressions
-/// 3. Updates all whiskermenu-*.rc files in the Whisker Menu directory
-///
-/// # Returns
-/// - `Ok(())` on successful update of all configurations
-/// - `Err(Box<dyn Error>)` if any file operations or regex operations fail
-pub fn update_whiskar_menu() -> Result<(), Box<dyn Error>> {
-    let theme_path = config::get_theme_path()?;
-    let whisker_menu_dir = config::get_whisker_menu_path()?;
-    let hex_code = config::get_base_color()?;
-    let opacity = config::get_opacity()?;
+/// What one pattern matched and changed in one file.
+#[derive(Debug, Clone)]
+pub struct PatternMatch {
+    /// Name of the pattern constant or profile target.
+    pub pattern: String,
+    /// The selector, key or property the pattern looks for.
+    pub selector: String,
+    pub path: PathBuf,
+    /// Number of rules, keys or properties the pattern matched.
+    pub count: usize,
+    /// How many of the matches got a different value; the rest already held it.
+    pub changed: usize,
+}
 
-    // let menu_opacity = opacity;
-    // let inverted_opacity = (((1.0 - opacity) * 100.0).round()) / 100.0;
+impl PatternMatch {
+    /// Describes a pattern that found nothing, naming the selector it looked for.
+    pub fn describe_unmatched(&self) -> String {
+        format!(
+            "{} found no `{}` in {}",
+            self.pattern,
+            self.selector,
+            self.path.display()
+        )
+    }
+}
 
-    let mut theme_content = fs::read_to_string(&theme_path)?;
-    let new_color = hex_to_rgba(&hex_code, 0.0)?;
-    let base_new_color = hex_to_rgba(&hex_code, 0.99)?;
+/// Replacement counts reported by the update functions.
+#[derive(Debug, Clone, Default)]
+pub struct UpdateReport {
+    pub matches: Vec<PatternMatch>,
+}
 
-    let base_menu_re = Regex::new(regex_patterns::PATTERN_BASE_MENU)?;
-    let menu_opacity_re = Regex::new(regex_patterns::PATTERN_MENU_OPACITY)?;
+impl UpdateReport {
+    /// Appends the matches of another report.
+    pub fn merge(&mut self, other: UpdateReport) {
+        self.matches.extend(other.matches);
+    }
 
-    theme_content = base_menu_re
-        .replace_all(&theme_content, |caps: &regex::Captures| {
-            caps[0].replace(&caps[1], &base_new_color)
-        })
-        .to_string();
+    /// Returns the patterns that matched nothing.
+    pub fn unmatched(&self) -> Vec<PatternMatch> {
+        self.matches
+            .iter()
+            .filter(|m| m.count == 0)
+            .cloned()
+            .collect()
+    }
 
-    theme_content = menu_opacity_re
-        .replace_all(&theme_content, |caps: &regex::Captures| {
-            caps[0].replace(&caps[1], &new_color)
-        })
-        .to_string();
+    /// Prints the number of matches and changes per pattern and file.
+    pub fn print_summary(&self) {
+        for m in &self.matches {
+            println!(
+                "{}: {} match(es), {} changed in {}",
+                m.pattern,
+                m.count,
+                m.changed,
+                m.path.display()
+            );
+        }
+    }
+
+    /// Fails if a required pattern did not match, or only warns in lenient mode.
+    ///
+    /// # Arguments
+    /// - `lenient`: Print warnings instead of returning an error.
+    ///
+    /// # Returns
+    /// - `Ok(())` if every pattern matched, or in lenient mode.
+    /// - `Err(UnmatchedPatternError)` listing every pattern that matched nothing.
+    pub fn check(&self, lenient: bool) -> Result<(), UnmatchedPatternError> {
+        let unmatched = self.unmatched();
+        if unmatched.is_empty() {
+            return Ok(());
+        }
+        if lenient {
+            for m in &unmatched {
+                eprintln!("warning: {}", m.describe_unmatched());
+            }
+            Ok(())
+        } else {
+            Err(UnmatchedPatternError { unmatched })
+        }
+    }
+}
 
-    fs::write(&theme_path, theme_content)?;
+/// Returned when a required pattern found nothing to replace.
+///
+/// This usually means the theme lays out the rule differently than the pattern expects,
+/// so applying would silently leave it unchanged.
+#[derive(Debug)]
+pub struct UnmatchedPatternError {
+    pub unmatched: Vec<PatternMatch>,
+}
 
+impl fmt::Display for UnmatchedPatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let details: Vec<String> = self
+            .unmatched
+            .iter()
+            .map(PatternMatch::describe_unmatched)
+            .collect();
+        write!(
+            f,
+            "{} (use --lenient to apply the remaining changes anyway)",
+            details.join("; ")
+        )
+    }
+}
+
+impl Error for UnmatchedPatternError {}
+
+/// Sets a CSS declaration in the theme and records the number of rules matched and changed.
+///
+/// The first candidate selector of the target that exists in the stylesheet is used.
+///
+/// # Arguments
+/// - `report`: The report the match count is recorded in.
+/// - `name`: Name of the profile target, used when reporting matches.
+/// - `target`: The candidate selectors and the property to set.
+/// - `path`: The theme file the content belongs to.
+/// - `content`: The stylesheet to update.
+/// - `value`: The new property value.
+fn set_css_counted(
+    report: &mut UpdateReport,
+    (name, target): (&str, &ProfileTarget),
+    path: &Path,
+    content: &str,
+    value: &str,
+) -> Result<String, Box<dyn Error>> {
+    let stylesheet = Stylesheet::parse(content).map_err(|e| format!("{}: {e}", path.display()))?;
+    let selector = target
+        .selectors
+        .iter()
+        .find(|selector| stylesheet.has_rule(selector));
+
+    let (updated, count, changed, selector) = match selector {
+        Some(selector) => {
+            let count = stylesheet.find_rules(selector).len();
+            let (updated, changed) = stylesheet.set_property(selector, &target.property, value);
+            (updated, count, changed, selector.clone())
+        }
+        None => (content.to_string(), 0, 0, target.selectors.join("` or `")),
+    };
+    report.matches.push(PatternMatch {
+        pattern: name.to_string(),
+        selector,
+        path: path.to_path_buf(),
+        count,
+        changed,
+    });
+    Ok(updated)
+}
+
+/// Lists all `whiskermenu-N.rc` files in the Whisker Menu directory.
+///
+/// # Returns
+/// - An `Ok(Vec<PathBuf>)` with the rc file paths, sorted by name.
+/// - An `Err` if the directory cannot be read.
+pub fn whisker_rc_files(whisker_menu_dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
     let whisker_menu_pattern = Regex::new(r"whiskermenu-\d+\.rc$")?;
-    let menu_opacity_re = Regex::new(regex_patterns::PATTERN_MENU_BASE_OPACITY)?;
+    let mut files = Vec::new();
 
     for entry in fs::read_dir(whisker_menu_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        let new_opacity = (opacity * 100.0) as u32;
-
+        let path = entry?.path();
         if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
             if whisker_menu_pattern.is_match(file_name) {
-                let mut content = fs::read_to_string(&path)?;
-                content = menu_opacity_re
-                    .replace_all(&content, |caps: &regex::Captures| {
-                        format!("{}{}", &caps[1], new_opacity)
-                    })
-                    .to_string();
-                fs::write(&path, content)?;
+                files.push(path);
             }
         }
     }
+    files.sort();
+    Ok(files)
+}
+
+/// Updates the Whisker Menu color and transparency in the linux theme configuration.
+///
+/// # Arguments
+/// - `config`: The loaded configuration providing the theme path, whisker menu directory,
+///   base color and opacity.
+/// - `profile`: The theme profile describing which rules to update.
+/// - `changes`: The changeset receiving the new file contents.
+///
+/// This function:
+/// 1. Updates the menu colors in the stylesheet with [`update_menu_colors`]
+/// 2. Sets `menu-opacity` and the `[whisker]` appearance keys in all whiskermenu-*.rc files
+///    in the Whisker Menu directory, applying per-instance overrides and inserting keys that
+///    are missing
+///
+/// # Returns
+/// - `Ok(UpdateReport)` with the matches and changes per pattern
+/// - `Err(Box<dyn Error>)` if any file operations or regex operations fail
+pub fn update_whiskar_menu(
+    config: &Config,
+    profile: &ThemeProfile,
+    changes: &mut Changeset,
+) -> Result<UpdateReport, Box<dyn Error>> {
+    let mut report = update_menu_colors(config, profile, changes)?;
+    let whisker_menu_dir = &config.whisker_menu_path;
+    let new_opacity = (config.opacity * 100.0).round() as u32;
+
+    for id in config.whisker.missing_instances(whisker_menu_dir) {
+        eprintln!(
+            "warning: [whisker.\"{id}\"] matches no rc file in {} (see `list-whiskers`)",
+            whisker_menu_dir.display()
+        );
+    }
+    for path in whisker_rc_files(whisker_menu_dir)? {
+        let id = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+        let settings = config.whisker.settings_for(id, new_opacity);
+        let mut rc = RcFile::parse(&changes.read(&path)?);
+        for (key, changed) in settings.apply_to(&mut rc) {
+            report.matches.push(PatternMatch {
+                pattern: key.to_string(),
+                selector: key.to_string(),
+                path: path.clone(),
+                count: 1,
+                changed: usize::from(changed),
+            });
+        }
+        changes.stage(&path, rc.to_string());
+    }
+    Ok(report)
+}
+
+/// Updates the Whisker Menu colors in the stylesheet only, leaving the rc files alone.
+///
+/// With the `theme` backend the profile's menu rules in `theme_path` are updated, with the
+/// `user` backend the menu block of the user stylesheet.
+///
+/// # Returns
+/// - `Ok(UpdateReport)` with the matches and changes per pattern
+/// - `Err(Box<dyn Error>)` if the stylesheet cannot be read or a color is invalid
+pub fn update_menu_colors(
+    config: &Config,
+    profile: &ThemeProfile,
+    changes: &mut Changeset,
+) -> Result<UpdateReport, Box<dyn Error>> {
+    let mut report = UpdateReport::default();
+    let base_color = config.color(&config.base_color)?;
+    let new_color = base_color.with_alpha(0.0).to_css();
+    let base_new_color = base_color.with_opacity(0.99).to_css();
+
+    if config.backend == Backend::User {
+        let body = user_css::menu_block(&base_new_color, &new_color);
+        stage_user_block(config, changes, "menu", &body)?;
+    } else {
+        update_theme_menu(
+            &mut report,
+            &config.theme_path,
+            profile,
+            changes,
+            (&base_new_color, &new_color),
+        )?;
+    }
+    Ok(report)
+}
+
+/// Sets the menu background and view rules in the theme stylesheet.
+///
+/// # Arguments
+/// - `report`: The report receiving the matches and changes per pattern.
+/// - `theme_path`: The theme stylesheet to edit.
+/// - `profile`: The theme profile describing which rules to update.
+/// - `changes`: The changeset receiving the new file contents.
+/// - `colors`: The menu background and the (transparent) view background.
+///
+/// # Errors
+/// Returns an error if the stylesheet cannot be read.
+fn update_theme_menu(
+    report: &mut UpdateReport,
+    theme_path: &Path,
+    profile: &ThemeProfile,
+    changes: &mut Changeset,
+    (background, view_background): (&str, &str),
+) -> Result<(), Box<dyn Error>> {
+    let mut theme_content = changes.read(theme_path)?;
+
+    theme_content = set_css_counted(
+        report,
+        ("menu_background", &profile.targets.menu_background),
+        theme_path,
+        &theme_content,
+        background,
+    )?;
+
+    theme_content = set_css_counted(
+        report,
+        ("menu_view", &profile.targets.menu_view),
+        theme_path,
+        &theme_content,
+        view_background,
+    )?;
+
+    changes.stage(theme_path, theme_content);
+    Ok(())
+}
+
+/// Writes a managed block into the user stylesheet of the `user` backend.
+///
+/// The stylesheet is created if it does not exist yet.
+///
+/// # Errors
+/// Returns an error if the existing stylesheet cannot be read.
+fn stage_user_block(
+    config: &Config,
+    changes: &mut Changeset,
+    id: &str,
+    body: &str,
+) -> Result<(), Box<dyn Error>> {
+    let path = &config.user_css_path;
+    let content = changes.read_or_empty(path)?;
+    let content = user_css::set_managed_block(&content, id, body);
+    changes.stage(path, content);
     Ok(())
 }
 
-/// Updates the search bar colors and transparency in the linux theme configuration. 
+/// Updates the search bar colors and transparency in the linux theme configuration.
 ///
 /// Applies the specified search color and opacity to both focused and unfocused
 /// search bar states in the theme file.
 ///
+/// # Arguments
+/// - `config`: The loaded configuration providing the theme path, search color and opacity.
+/// - `profile`: The theme profile describing which rules to update.
+/// - `changes`: The changeset receiving the new file contents.
+///
 /// # Returns
-/// - `Ok(())` on successful update
+/// - `Ok(UpdateReport)` with the matches and changes per pattern
 /// - `Err(Box<dyn Error>)` for file I/O or regex errors
-pub fn update_search_bar() -> Result<(), Box<dyn Error>> {
-    let path = config::get_theme_path()?;
-    let hex_code = config::get_search_color()?;
-    let opacity = config::get_search_opacity()?;
+pub fn update_search_bar(
+    config: &Config,
+    profile: &ThemeProfile,
+    changes: &mut Changeset,
+) -> Result<UpdateReport, Box<dyn Error>> {
+    let mut report = UpdateReport::default();
+    let path = &config.theme_path;
+    let new_color = config
+        .color(&config.search_color)?
+        .with_opacity(config.search_opacity)
+        .to_css();
+
+    if config.backend == Backend::User {
+        stage_user_block(
+            config,
+            changes,
+            "search",
+            &user_css::search_block(&new_color),
+        )?;
+        return Ok(report);
+    }
 
-    let mut content = fs::read_to_string(&path)?;
+    let mut content = changes.read(path)?;
 
-    let new_color = hex_to_rgba(&hex_code, opacity)?;
+    content = set_css_counted(
+        &mut report,
+        ("search_focused", &profile.targets.search_focused),
+        path,
+        &content,
+        &new_color,
+    )?;
 
-    let search_focus_re = Regex::new(regex_patterns::PATTERN_SEARCH_FOCUS)?;
-    let search_unfocused_re = Regex::new(regex_patterns::PATTERN_SEARCH_UNFOCUSED)?;
+    content = set_css_counted(
+        &mut report,
+        ("search_unfocused", &profile.targets.search_unfocused),
+        path,
+        &content,
+        &new_color,
+    )?;
 
-    content = search_focus_re
-        .replace_all(&content, |caps: &regex::Captures| {
-            caps[0].replace(&caps[1], &new_color)
-        })
-        .to_string();
+    changes.stage(path, content);
+    Ok(report)
+}
 
-    content = search_unfocused_re
-        .replace_all(&content, |caps: &regex::Captures| {
-            caps[0].replace(&caps[1], &new_color)
+/// Converts a color to the `background-rgba` array of a panel.
+fn panel_rgba(color: Color) -> XfconfValue {
+    XfconfValue::Array(color.to_xfconf().map(XfconfValue::Double).to_vec())
+}
+
+/// Lists the `panel-N` IDs of the panel channel, with whether the panel has a
+/// `background-rgba` array.
+fn panels(channel: &Channel) -> Vec<(String, bool)> {
+    channel
+        .property("/panels")
+        .map_or(&[][..], |panels| &panels.properties)
+        .iter()
+        .filter_map(|panel| {
+            let id = numbered(&panel.name, "panel-")?;
+            Some((id.to_string(), panel.child("background-rgba").is_some()))
         })
-        .to_string();
+        .collect()
+}
 
-    fs::write(&path, content)?;
-    Ok(())
+/// Computes the properties `--updatepanel` sets in the `xfce4-panel` channel.
+///
+/// Every panel gets `background-rgba` from the base color and opacity, or from those of its
+/// `[panel.N]` section. Panels without a `background-rgba` array also get `background-style`
+/// set to solid color (1) so it takes effect.
+///
+/// # Returns
+/// - An `Ok(Vec)` of property paths and values, in panel order.
+/// - An `Err` if a color is invalid.
+pub fn panel_properties(
+    config: &Config,
+    channel: &Channel,
+) -> Result<Vec<(String, XfconfValue)>, Box<dyn Error>> {
+    let mut properties = Vec::new();
+    for (id, has_rgba) in panels(channel) {
+        let overrides = config.panel.get(&id);
+        let color = overrides
+            .and_then(|panel| panel.base_color.as_deref())
+            .unwrap_or(&config.base_color);
+        let opacity = overrides
+            .and_then(|panel| panel.opacity)
+            .unwrap_or(config.opacity);
+
+        let path = format!("/panels/panel-{id}");
+        properties.push((
+            format!("{path}/background-rgba"),
+            panel_rgba(config.color(color)?.with_opacity(opacity)),
+        ));
+        if !has_rgba {
+            properties.push((format!("{path}/background-style"), XfconfValue::Uint(1)));
+        }
+    }
+    Ok(properties)
 }
 
 /// Updates the panel background color and transparency.
 ///
-/// Converts the base color and opacity to normalized RGBA values and updates
-/// the panel configuration XML file.
+/// Sets the properties computed by [`panel_properties`] in the xfconf panel channel file.
+///
+/// # Arguments
+/// - `config`: The loaded configuration providing the panel path, colors and opacities.
+/// - `changes`: The changeset receiving the new file contents.
 ///
 /// # Returns
-/// - `Ok(())` on successful write
-/// - `Err(Box<dyn Error>)` for file operations or color conversion errors
-pub fn update_panel() -> Result<(), Box<dyn Error>> {
-    let panel_path = config::get_panel_path()?;
-    let hex_code = config::get_base_color()?;
-    let opacity = config::get_opacity()?;
-
-    let mut content = fs::read_to_string(&panel_path)?;
-
-    let rgba_values = hex_to_normalized_rgba(&hex_code, opacity)?;
-
-    let panel_background_re = Regex::new(regex_patterns::PATTERN_PANEL_BACKGROUND_RGBA)?;
-
-    content = panel_background_re
-        .replace_all(&content, |_caps: &regex::Captures| {
-            format!(
-                r#"<property name="background-rgba" type="array">
-        <value type="double" value="{}"/>
-        <value type="double" value="{}"/>
-        <value type="double" value="{}"/>
-        <value type="double" value="{}"/>
-      </property>"#,
-                rgba_values[0], rgba_values[1], rgba_values[2], rgba_values[3]
-            )
-        })
-        .to_string();
+/// - `Ok(UpdateReport)` with one entry per panel updated
+/// - `Err(Box<dyn Error>)` for file operations, XML parsing or color conversion errors
+pub fn update_panel(
+    config: &Config,
+    changes: &mut Changeset,
+) -> Result<UpdateReport, Box<dyn Error>> {
+    let mut report = UpdateReport::default();
+    let panel_path = &config.panel_path;
 
-    fs::write(&panel_path, content)?;
-    Ok(())
+    let content = changes.read(panel_path)?;
+    let mut channel =
+        Channel::parse(&content).map_err(|e| format!("{}: {e}", panel_path.display()))?;
+
+    let panels = panels(&channel);
+    for id in config.panel.keys() {
+        if !panels.iter().any(|(panel, _)| panel == id) {
+            eprintln!(
+                "warning: [panel.{id}] matches no panel-{id} in {}",
+                panel_path.display()
+            );
+        }
+    }
+
+    for (path, value) in panel_properties(config, &channel)? {
+        let previous = channel.property(&path).map(Property::value).transpose()?;
+        channel.set(&path, &value)?;
+        let Some(panel) = path.strip_suffix("/background-rgba") else {
+            continue;
+        };
+        report.matches.push(PatternMatch {
+            pattern: format!("{} background-rgba", panel.trim_start_matches("/panels/")),
+            selector: path.clone(),
+            path: panel_path.to_path_buf(),
+            count: 1,
+            changed: usize::from(previous.as_ref() != Some(&value)),
+        });
+    }
+    if panels.is_empty() {
+        report.matches.push(PatternMatch {
+            pattern: "panel background-rgba".to_string(),
+            selector: "/panels/panel-N".to_string(),
+            path: panel_path.to_path_buf(),
+            count: 0,
+            changed: 0,
+        });
+    }
+
+    changes.stage(panel_path, channel.to_string());
+    Ok(report)
 }
 
 /// Updates whiskar menu border colors in the linux theme configuration.
 ///
 /// Applies the base color to all borders in the whisker menu.
 ///
+/// # Arguments
+/// - `config`: The loaded configuration providing the theme path and base color.
+/// - `profile`: The theme profile describing which rules to update.
+/// - `changes`: The changeset receiving the new file contents.
+///
 /// # Returns
-/// - `Ok(())` on success
+/// - `Ok(UpdateReport)` with the matches and changes per pattern
 /// - `Err(Box<dyn Error>)` for I/O or regex errors
-pub fn update_border() -> Result<(), Box<dyn Error>> {
-    let theme_path = config::get_theme_path()?;
-    let border_color = config::get_base_color()?;
+pub fn update_border(
+    config: &Config,
+    profile: &ThemeProfile,
+    changes: &mut Changeset,
+) -> Result<UpdateReport, Box<dyn Error>> {
+    let mut report = UpdateReport::default();
+    let theme_path = &config.theme_path;
+    let border_color = &config.color(&config.border_color)?.to_css();
 
-    let mut content = fs::read_to_string(&theme_path)?;
+    if config.backend == Backend::User {
+        stage_user_block(
+            config,
+            changes,
+            "border",
+            &user_css::border_block(border_color),
+        )?;
+        return Ok(report);
+    }
 
-    let border_re = Regex::new(regex_patterns::PATTERN_BORDER_COLOR)?;
+    let content = changes.read(theme_path)?;
 
-    content = border_re
-        .replace_all(&content, |caps: &regex::Captures| {
-            caps[0].replace(&caps[1], &border_color)
-        })
-        .to_string();
+    let content = set_css_counted(
+        &mut report,
+        ("border", &profile.targets.border),
+        theme_path,
+        &content,
+        border_color,
+    )?;
 
-    fs::write(&theme_path, content)?;
-    Ok(())
+    changes.stage(theme_path, content);
+    Ok(report)
 }
@@ -1,160 +1,692 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::fmt;
 use std::fs;
-use toml::Value;
+use std::path::{Path, PathBuf};
 
 use std::env;
-use std::path::PathBuf;
 
-/// Creates a default configuration file (`config.toml`) in the current directory.
-///
-/// This function generates a TOML-formatted configuration file with default paths and settings
-/// for customizing the Xfce Whisker Menu, panel, and related components. The file is saved in the
-/// current working directory as `config.toml`.
-///
-/// # Default Configuration
-/// The generated configuration file includes the following fields:
-/// - `theme_path`: Path to the GTK theme CSS file (default: `/usr/share/themes/Mint-L-Dark/gtk-3.0/gtk-dark.css`).
-/// - `whisker_menu_path`: Path to the Whisker Menu configuration directory (default: `~/.config/xfce4/panel/`).
-/// - `panel_path`: Path to the Xfce panel configuration file (default: `~/.config/xfce4/xfconf/xfce-perchannel-xml/xfce4-panel.xml`).
-/// - `base_color`: Base color for the UI components (default: `#000000`).
-/// - `opacity`: Opacity level for the UI components (default: `0.0`).
-/// - `search_color`: Color for the search bar (default: `#000000`).
-/// - `search_opacity`: Opacity level for the search bar (default: `0.0`).
+use crate::changeset::Changeset;
+use crate::color::Color;
+use crate::color_expr::{is_color_name, ColorDefinitions, ColorScope};
+use crate::css::define_colors;
+use crate::detect::detect_theme;
+use crate::paths::{expand_path, xdg_config_dirs, xdg_config_home, APP_DIR_NAME};
+use crate::whisker_rc::WhiskerConfig;
+
+/// Typed representation of `config.toml`.
 ///
-/// # Returns
-/// - `Ok(())` if the configuration file is successfully created.
-/// - `Err(Box<dyn Error>)` if an error occurs during file creation or writing.
-pub fn create_default_config() -> Result<(), Box<dyn Error>> {
-    let home_dir = env::var("HOME").unwrap_or_else(|_| "/home".to_string());
+/// The configuration is read and validated once at startup and then passed by reference
+/// to every update function, so a bad value is reported before any file is modified.
+/// Paths may use `~`, `$VAR` and `${VAR}`; they are expanded while loading.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Path to the gtk-3.0 theme CSS file (e.g. `gtk-dark.css`).
+    pub theme_path: PathBuf,
+    /// Directory containing the `whiskermenu-N.rc` files.
+    pub whisker_menu_path: PathBuf,
+    /// Path to the `xfce4-panel.xml` xfconf channel file.
+    pub panel_path: PathBuf,
+    /// Base color used for the whisker menu and the panel. Like every color key, this is a
+    /// color expression evaluated by [`ColorScope`].
+    pub base_color: String,
+    /// Opacity applied to the base color.
+    pub opacity: f64,
+    /// Color used for the whisker menu search bar.
+    pub search_color: String,
+    /// Opacity applied to the search color.
+    pub search_opacity: f64,
+    /// Color of the whisker menu borders.
+    #[serde(default = "default_border_color")]
+    pub border_color: String,
+    /// Optional `[colors]` section naming extra colors for use in expressions.
+    #[serde(default)]
+    pub colors: BTreeMap<String, String>,
+    /// Where the menu colors are written.
+    #[serde(default)]
+    pub backend: Backend,
+    /// User stylesheet written by the `user` backend.
+    #[serde(default = "default_user_css_path")]
+    pub user_css_path: PathBuf,
+    /// How xfconf properties (the panel background, the theme set by `fork-theme --switch`) are
+    /// written.
+    #[serde(default)]
+    pub xfconf: XfconfMode,
+    /// Name of the theme profile to use; chosen from the theme name when unset.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Optional `[panel.N]` sections overriding the color and opacity of `panel-N`.
+    #[serde(default)]
+    pub panel: BTreeMap<String, PanelConfig>,
+    /// Optional `[whisker]` section with the appearance keys of the rc files, and
+    /// `[whisker."whiskermenu-N"]` overrides per plugin instance.
+    #[serde(default)]
+    pub whisker: WhiskerConfig,
+    /// Optional `[backup]` section.
+    #[serde(default)]
+    pub backup: BackupConfig,
+    /// `@define-color` definitions of the theme stylesheet, loaded when a color refers to one.
+    #[serde(skip)]
+    pub color_definitions: ColorDefinitions,
+}
 
-    let config_content = format!(
-        r##"# default_paths
-theme_path = '/usr/share/themes/Mint-L-Dark/gtk-3.0/gtk-dark.css'
-whisker_menu_path = '{home_dir}/.config/xfce4/panel/'
-panel_path = '{home_dir}/.config/xfce4/xfconf/xfce-perchannel-xml/xfce4-panel.xml'
+/// Where the Whisker Menu colors are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Patch the theme stylesheet at `theme_path` (usually needs root).
+    #[default]
+    Theme,
+    /// Write `#whiskermenu-window` rules into the user's `gtk-3.0/gtk.css`, which needs no
+    /// root and survives theme package upgrades.
+    User,
+}
 
-# colors
-base_color = "#000000"
-opacity = 0.0
-search_color = "#000000"
-search_opacity = 0.0"##
-    );
+/// How xfconf properties (the panel background, the theme set by `fork-theme --switch`) are
+/// written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum XfconfMode {
+    /// Use xfconfd over D-Bus when it is available, else write the channel file.
+    #[default]
+    Auto,
+    /// Always use xfconfd over D-Bus and fail if it is not available.
+    Dbus,
+    /// Always write the channel files directly (stop xfconfd first).
+    File,
+}
+
+fn default_border_color() -> String {
+    "base".to_string()
+}
+
+fn default_user_css_path() -> PathBuf {
+    PathBuf::from("$XDG_CONFIG_HOME/gtk-3.0/gtk.css")
+}
+
+/// Settings of a `[panel.N]` section; unset keys fall back to the top-level values.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PanelConfig {
+    /// Background color of this panel.
+    pub base_color: Option<String>,
+    /// Background opacity of this panel.
+    pub opacity: Option<f64>,
+}
+
+/// Settings of the `[backup]` section.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Whether files are snapshotted before every update.
+    pub enabled: bool,
+    /// Number of snapshots to keep; older ones are pruned.
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        BackupConfig {
+            enabled: true,
+            keep: 10,
+        }
+    }
+}
+
+impl Config {
+    /// Loads and validates the configuration from the given `config.toml` file.
+    ///
+    /// # Arguments
+    /// - `path`: Path of the configuration file, usually found with [`locate_config`].
+    ///
+    /// # Returns
+    /// - An `Ok(Config)` containing the validated configuration.
+    /// - An `Err` if the file cannot be read or parsed, or if any value is invalid.
+    pub fn load(path: &Path) -> Result<Config, Box<dyn Error>> {
+        let config_content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let config =
+            Config::from_toml(&config_content).map_err(|e| format!("{}: {e}", path.display()))?;
+        println!("Config was Loaded from {}", path.display());
+        Ok(config)
+    }
+
+    /// Parses and validates a configuration from a TOML string.
+    ///
+    /// # Returns
+    /// - An `Ok(Config)` if every key is present and valid.
+    /// - An `Err` describing the first missing or invalid value.
+    pub fn from_toml(content: &str) -> Result<Config, Box<dyn Error>> {
+        let mut config: Config =
+            toml::from_str(content).map_err(|e| format!("Invalid config.toml: {e}"))?;
+        config.expand_paths()?;
+        config.load_color_definitions()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Expands `~` and environment variables in every configured path.
+    fn expand_paths(&mut self) -> Result<(), Box<dyn Error>> {
+        for path in [
+            &mut self.theme_path,
+            &mut self.whisker_menu_path,
+            &mut self.panel_path,
+            &mut self.user_css_path,
+        ] {
+            *path = expand_path(&path.to_string_lossy())?;
+        }
+        Ok(())
+    }
+
+    /// Reads the `@define-color` definitions of `theme_path` if any color refers to one.
+    ///
+    /// # Errors
+    /// Returns an error if a color refers to a definition and the theme cannot be read.
+    fn load_color_definitions(&mut self) -> Result<(), Box<dyn Error>> {
+        let panel_colors = self.panel.values().filter_map(|p| p.base_color.as_ref());
+        let mut colors = [&self.base_color, &self.search_color, &self.border_color]
+            .into_iter()
+            .chain(self.colors.values())
+            .chain(panel_colors);
+        if !colors.any(|color| color.contains('@')) {
+            return Ok(());
+        }
+        let content = fs::read_to_string(&self.theme_path).map_err(|e| {
+            format!(
+                "Failed to read {} for @define-color references: {e}",
+                self.theme_path.display()
+            )
+        })?;
+        self.color_definitions =
+            define_colors(&content).map_err(|e| format!("{}: {e}", self.theme_path.display()))?;
+        Ok(())
+    }
+
+    /// Returns the names color expressions can use: `base`, `search`, `border` and the
+    /// entries of `[colors]`, along with the theme's `@define-color` definitions.
+    pub fn color_scope(&self) -> ColorScope<'_> {
+        let mut names: BTreeMap<&str, &str> = self
+            .colors
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        names.insert("base", &self.base_color);
+        names.insert("search", &self.search_color);
+        names.insert("border", &self.border_color);
+        ColorScope {
+            names,
+            definitions: &self.color_definitions,
+        }
+    }
+
+    /// Evaluates a configured color expression.
+    ///
+    /// # Errors
+    /// Returns an error if the value is not a valid color expression.
+    pub fn color(&self, value: &str) -> Result<Color, Box<dyn Error>> {
+        self.color_scope().evaluate(value)
+    }
 
-    let config_path = PathBuf::from("./config.toml");
-    fs::write(config_path, config_content)?;
-    println!("Created default config.toml in the current directory");
+    /// Checks every value of the configuration.
+    ///
+    /// # Errors
+    /// - A color expression cannot be evaluated, or a `[colors]` name is invalid.
+    /// - A `[panel.N]` section is not named by a panel number.
+    /// - An opacity is not between 0.0 and 1.0.
+    /// - A `[whisker]` key is out of range.
+    /// - A configured path does not exist (`theme_path` is only checked for the `theme` backend).
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        self.validate_color("base_color", &self.base_color)?;
+        self.validate_color("search_color", &self.search_color)?;
+        self.validate_color("border_color", &self.border_color)?;
+        for (name, value) in &self.colors {
+            if !is_color_name(name) || RESERVED_COLOR_NAMES.contains(&name.as_str()) {
+                return Err(format!(
+                    "Invalid color name '{name}' in [colors]. Use letters, digits, '_' and '-', \
+                     starting with a letter; base, search and border are taken."
+                )
+                .into());
+            }
+            self.validate_color(&format!("colors.{name}"), value)?;
+        }
+        validate_opacity("opacity", self.opacity)?;
+        validate_opacity("search_opacity", self.search_opacity)?;
+        if self.backend == Backend::Theme {
+            validate_path("theme_path", &self.theme_path, false)?;
+        }
+        validate_path("whisker_menu_path", &self.whisker_menu_path, true)?;
+        for (id, panel) in &self.panel {
+            if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(format!(
+                    "Invalid section [panel.{id}]. Use the panel number, e.g. [panel.1]."
+                )
+                .into());
+            }
+            if let Some(color) = &panel.base_color {
+                self.validate_color(&format!("panel.{id}.base_color"), color)?;
+            }
+            if let Some(opacity) = panel.opacity {
+                validate_opacity(&format!("panel.{id}.opacity"), opacity)?;
+            }
+        }
+        self.whisker.validate()?;
+        validate_path("panel_path", &self.panel_path, false)?;
+        Ok(())
+    }
+
+    fn validate_color(&self, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+        self.color(value)
+            .map(|_| ())
+            .map_err(|e| format!("Invalid {key}: {e}").into())
+    }
+}
+
+/// Names that refer to the top-level color keys in expressions.
+const RESERVED_COLOR_NAMES: [&str; 3] = ["base", "search", "border"];
+
+fn validate_opacity(key: &str, value: f64) -> Result<(), Box<dyn Error>> {
+    if !(0.0..=1.0).contains(&value) {
+        return Err(format!("Invalid {key} {value}. Must be between 0 and 1.").into());
+    }
     Ok(())
 }
 
-/// Loads the configuration from the `config.toml` file.
-///
-/// # Returns
-/// - An `Ok(Value)` containing the parsed TOML configuration if successful.
-/// - An `Err` if the file cannot be read or parsed, or if the file is missing.
-fn load_config() -> Result<Value, Box<dyn Error>> {
-    let config_content = fs::read_to_string("./config.toml")?;
-    print!("Config was Loaded");
-    let config: Value = toml::from_str(&config_content)?;
-    Ok(config)
+fn validate_path(key: &str, path: &Path, is_dir: bool) -> Result<(), Box<dyn Error>> {
+    if !path.exists() {
+        return Err(format!("{key} '{}' does not exist", path.display()).into());
+    }
+    if is_dir && !path.is_dir() {
+        return Err(format!("{key} '{}' is not a directory", path.display()).into());
+    }
+    if !is_dir && !path.is_file() {
+        return Err(format!("{key} '{}' is not a file", path.display()).into());
+    }
+    Ok(())
 }
 
-/// Retrieves your gtk-3.0 linux theme path from the configuration.
+/// Sets a top-level key in the contents of a TOML config file.
 ///
-/// # Returns
-/// - An `Ok(String)` containing the theme path if found.
-/// - An `Err` if the `theme_path` key is missing or not a valid string.
-pub fn get_theme_path() -> Result<String, Box<dyn Error>> {
-    let config: Value = load_config()?;
+/// The assignment of the key before the first `[section]` is replaced in place, keeping the
+/// rest of the file and its comments; if the key is missing it is added after the last
+/// top-level line.
+///
+/// # Errors
+/// Returns an error if the key cannot be turned into a pattern.
+pub fn set_config_key(
+    content: &str,
+    key: &str,
+    value: &toml::Value,
+) -> Result<String, Box<dyn Error>> {
+    let assignment = format!("{key} = {value}");
+    let header_re = Regex::new(r"(?m)^[ \t]*\[")?;
+    let top_level_end = header_re.find(content).map_or(content.len(), |m| m.start());
+    let (top_level, sections) = content.split_at(top_level_end);
+    let key_re = Regex::new(&format!(r"(?m)^[ \t]*{}[ \t]*=.*$", regex::escape(key)))?;
+    if key_re.is_match(top_level) {
+        let top_level = key_re.replace(top_level, assignment.as_str());
+        return Ok(format!("{top_level}{sections}"));
+    }
+    let top_level = top_level.trim_end();
+    let separator = if sections.is_empty() { "" } else { "\n" };
+    if top_level.is_empty() {
+        return Ok(format!("{assignment}\n{separator}{sections}"));
+    }
+    Ok(format!("{top_level}\n{assignment}\n{separator}{sections}"))
+}
 
-    Ok(config["theme_path"]
-        .as_str()
-        .ok_or("Theme path not found in config")?
-        .to_string())
+/// Sets a key of a `[section]` table in the contents of a TOML config file.
+///
+/// An existing assignment in the section is replaced in place; otherwise the key is added
+/// right after the section header, and a missing section is appended to the file.
+///
+/// # Errors
+/// Returns an error if the section or key cannot be turned into a pattern.
+pub fn set_section_key(
+    content: &str,
+    section: &str,
+    key: &str,
+    value: &toml::Value,
+) -> Result<String, Box<dyn Error>> {
+    let assignment = format!("{key} = {value}");
+    let section_re = Regex::new(&format!(
+        r"(?m)^[ \t]*\[[ \t]*{}[ \t]*\][^\n]*\n?",
+        regex::escape(section)
+    ))?;
+    let Some(header) = section_re.find(content) else {
+        let mut updated = content.to_string();
+        if !updated.is_empty() && !updated.ends_with('\n') {
+            updated.push('\n');
+        }
+        updated.push_str(&format!("\n[{section}]\n{assignment}\n"));
+        return Ok(updated);
+    };
+    let header_re = Regex::new(r"(?m)^[ \t]*\[")?;
+    let body_end = header_re
+        .find(&content[header.end()..])
+        .map_or(content.len(), |m| header.end() + m.start());
+    let body = &content[header.end()..body_end];
+    let key_re = Regex::new(&format!(r"(?m)^[ \t]*{}[ \t]*=.*$", regex::escape(key)))?;
+    let body = if key_re.is_match(body) {
+        key_re.replace(body, assignment.as_str()).into_owned()
+    } else {
+        format!("{assignment}\n{body}")
+    };
+    Ok(format!(
+        "{}{body}{}",
+        &content[..header.end()],
+        &content[body_end..]
+    ))
 }
 
-/// Retrieves the panel configuration file   path from the configuration.
+/// Sets keys in the config file, keeping everything else in it as it is.
 ///
-/// # Returns
-/// - An `Ok(String)` containing the panel path if found.
-/// - An `Err` if the `panel_path` key is missing or not a valid string.
-pub fn get_panel_path() -> Result<String, Box<dyn Error>> {
-    let config: Value = load_config()?;
+/// Keys are top-level names or `section.key`.
+///
+/// # Arguments
+/// - `config_path`: The config file to update.
+/// - `keys`: The keys to set, with their new values.
+/// - `dry_run`: Only print the changes.
+///
+/// # Errors
+/// Returns an error if the config file cannot be read or written.
+pub fn update_config_keys(
+    config_path: &Path,
+    keys: &[(&str, toml::Value)],
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut changes = Changeset::new();
+    let mut content = changes.read(config_path)?;
+    for (key, value) in keys {
+        content = match key.split_once('.') {
+            Some((section, key)) => set_section_key(&content, section, key, value)?,
+            None => set_config_key(&content, key, value)?,
+        };
+    }
+    changes.stage(config_path, content);
 
-    Ok(config["panel_path"]
-        .as_str()
-        .ok_or("Panel path not found in config")?
-        .to_string())
+    if dry_run {
+        changes.print_diff();
+        return Ok(());
+    }
+    changes.commit()?;
+    println!("Updated {}", config_path.display());
+    Ok(())
 }
 
+/// Name of the environment variable that may point to the configuration file.
+pub const CONFIG_ENV_VAR: &str = "XFCE4_TRANSPARENT_WHISKERMENU_CONFIG";
 
-/// Retrieves the whisker menu configuration file path from the configuration.
-///
-/// # Returns
-/// - An `Ok(String)` containing the whisker menu path if found.
-/// - An `Err` if the `whisker_menu_path` key is missing or not a valid string.
-pub fn get_whisker_menu_path() -> Result<String, Box<dyn Error>> {
-    let config: Value = load_config()?;
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Theme stylesheet written by `--createconfig` when the active theme cannot be detected.
+const DEFAULT_THEME_PATH: &str = "/usr/share/themes/Mint-L-Dark/gtk-3.0/gtk-dark.css";
 
-    Ok(config["whisker_menu_path"]
-        .as_str()
-        .ok_or("Whisker menu path not found in config")?
-        .to_string())
+/// Describes where a configuration file candidate comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Passed explicitly with `--config <path>`.
+    Flag,
+    /// Taken from the `XFCE4_TRANSPARENT_WHISKERMENU_CONFIG` environment variable.
+    Env,
+    /// Found in the user configuration directory (`$XDG_CONFIG_HOME`).
+    XdgConfigHome,
+    /// Found in one of the system configuration directories (`$XDG_CONFIG_DIRS`).
+    XdgConfigDirs,
+    /// Legacy `./config.toml` in the current working directory.
+    WorkingDirectory,
 }
 
-/// Retrieves the base color from the configuration.
-/// This color is used for the whisker menu border and main color.
-///
-/// # Returns
-/// - An `Ok(String)` containing the base color if found.
-/// - An `Err` if the `base_color` key is missing or not a valid string
-pub fn get_base_color() -> Result<String, Box<dyn Error>> {
-    let config: Value = load_config()?;
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            ConfigSource::Flag => "passed with --config",
+            ConfigSource::Env => "set by $XFCE4_TRANSPARENT_WHISKERMENU_CONFIG",
+            ConfigSource::XdgConfigHome => "user config in $XDG_CONFIG_HOME",
+            ConfigSource::XdgConfigDirs => "system config in $XDG_CONFIG_DIRS",
+            ConfigSource::WorkingDirectory => "legacy config in the current directory",
+        };
+        write!(f, "{reason}")
+    }
+}
 
-    Ok(config["base_color"]
-        .as_str()
-        .ok_or("Base color not found in config")?
-        .to_string())
+/// A configuration file path together with the reason it was chosen.
+#[derive(Debug, Clone)]
+pub struct ConfigLocation {
+    pub path: PathBuf,
+    pub source: ConfigSource,
 }
 
-/// Retrieves the opacity value from the configuration.
-/// This color is used for the whisker menu main color.
-/// 
-/// # Returns
-/// - An `Ok(f32)` containing the opacity value if found.
-/// - An `Err` if the `opacity` key is missing or not a valid float.
-pub fn get_opacity() -> Result<f32, Box<dyn Error>> {
-    let config: Value = load_config()?;
+/// Returns the path where `--createconfig` writes a new configuration file.
+pub fn default_config_path() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(xdg_config_home()?.join(APP_DIR_NAME).join(CONFIG_FILE_NAME))
+}
+
+/// Lists every configuration file candidate in lookup order.
+///
+/// The lookup chain is:
+/// 1. `--config <path>`
+/// 2. `$XFCE4_TRANSPARENT_WHISKERMENU_CONFIG`
+/// 3. `$XDG_CONFIG_HOME/xfce4-transparent-whiskermenu/config.toml`
+/// 4. `$XDG_CONFIG_DIRS/xfce4-transparent-whiskermenu/config.toml` (default `/etc/xdg`)
+/// 5. `./config.toml` for configurations created by older versions
+pub fn config_candidates(explicit: Option<&Path>) -> Vec<ConfigLocation> {
+    let mut candidates = Vec::new();
 
-    Ok(config["opacity"]
-        .as_float()
-        .ok_or("Opacity not found in config")? as f32)
+    if let Some(path) = explicit {
+        candidates.push(ConfigLocation {
+            path: path.to_path_buf(),
+            source: ConfigSource::Flag,
+        });
+    }
+    if let Some(path) = env::var_os(CONFIG_ENV_VAR).filter(|p| !p.is_empty()) {
+        candidates.push(ConfigLocation {
+            path: PathBuf::from(path),
+            source: ConfigSource::Env,
+        });
+    }
+    if let Ok(config_home) = xdg_config_home() {
+        candidates.push(ConfigLocation {
+            path: config_home.join(APP_DIR_NAME).join(CONFIG_FILE_NAME),
+            source: ConfigSource::XdgConfigHome,
+        });
+    }
+    for dir in xdg_config_dirs() {
+        candidates.push(ConfigLocation {
+            path: dir.join(APP_DIR_NAME).join(CONFIG_FILE_NAME),
+            source: ConfigSource::XdgConfigDirs,
+        });
+    }
+    candidates.push(ConfigLocation {
+        path: PathBuf::from(".").join(CONFIG_FILE_NAME),
+        source: ConfigSource::WorkingDirectory,
+    });
+
+    candidates
 }
 
-/// Retrieves the search color from the configuration.
-/// This color is used for the search bar inside of whisker menu.
+/// Finds the configuration file to use.
+///
+/// A path given with `--config` or the environment variable must exist; it is never
+/// silently replaced by a file further down the lookup chain.
 ///
 /// # Returns
-/// - An `Ok(String)` containing the search color if found.
-/// - An `Err` if the `search_color` key is missing or not a valid string.
-pub fn get_search_color() -> Result<String, Box<dyn Error>> {
-    let config: Value = load_config()?;
+/// - An `Ok(ConfigLocation)` with the first existing candidate.
+/// - An `Err` if an explicit path does not exist or no candidate was found.
+pub fn locate_config(explicit: Option<&Path>) -> Result<ConfigLocation, Box<dyn Error>> {
+    let candidates = config_candidates(explicit);
 
-    Ok(config["search_color"]
-        .as_str()
-        .ok_or("Search color not found in config")?
-        .to_string())
+    for candidate in &candidates {
+        if candidate.path.is_file() {
+            return Ok(candidate.clone());
+        }
+        if matches!(candidate.source, ConfigSource::Flag | ConfigSource::Env) {
+            return Err(format!(
+                "Config file '{}' ({}) does not exist",
+                candidate.path.display(),
+                candidate.source
+            )
+            .into());
+        }
+    }
+
+    let searched: Vec<String> = candidates
+        .iter()
+        .map(|c| c.path.display().to_string())
+        .collect();
+    Err(format!(
+        "No config file found (searched: {}). Run with --createconfig to create one.",
+        searched.join(", ")
+    )
+    .into())
+}
+
+/// Prints the configuration file that would be used and why it was chosen.
+///
+/// # Arguments
+/// - `explicit`: The path passed with `--config`, if any.
+pub fn print_config_path(explicit: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let location = locate_config(explicit)?;
+    println!("{}", location.path.display());
+    println!("  reason: {}", location.source);
+    for candidate in config_candidates(explicit) {
+        if candidate.path == location.path {
+            break;
+        }
+        println!(
+            "  skipped: {} ({}, not found)",
+            candidate.path.display(),
+            candidate.source
+        );
+    }
+    Ok(())
 }
 
-/// Retrieves the search opacity value from the configuration.
-/// This opacity is applied to the search bar color.
+/// Creates a default configuration file (`config.toml`).
+///
+/// This function generates a TOML-formatted configuration file with default paths and settings
+/// for customizing the Xfce Whisker Menu, panel, and related components. The file is saved at
+/// the path given with `--config`, or at `$XDG_CONFIG_HOME/xfce4-transparent-whiskermenu/config.toml`.
+/// An existing file is never overwritten.
+///
+/// # Default Configuration
+/// The generated configuration file includes the following fields:
+/// - `theme_path`: Path to the GTK theme CSS file (default: the stylesheet of the active theme in
+///   `xsettings.xml`, or `/usr/share/themes/Mint-L-Dark/gtk-3.0/gtk-dark.css` if detection fails).
+/// - `whisker_menu_path`: Path to the Whisker Menu configuration directory (default: `~/.config/xfce4/panel/`).
+/// - `panel_path`: Path to the Xfce panel configuration file (default: `~/.config/xfce4/xfconf/xfce-perchannel-xml/xfce4-panel.xml`).
+/// - `base_color`: Base color for the UI components (default: `#000000`).
+/// - `opacity`: Opacity level for the UI components (default: `0.0`).
+/// - `search_color`: Color for the search bar (default: `#000000`).
+/// - `search_opacity`: Opacity level for the search bar (default: `0.0`).
+/// - `border_color`: Color of the menu borders (default: `base`, the base color).
+/// - `[colors]`: Extra named colors for color expressions (commented out).
+/// - `backend`: `theme` to patch the theme stylesheet, or `user` to write `~/.config/gtk-3.0/gtk.css` (default: `theme`).
+/// - `user_css_path`: Stylesheet written by the `user` backend (default: `~/.config/gtk-3.0/gtk.css`).
+/// - `xfconf`: `auto`, `dbus` or `file`: how panel properties are written (default: `auto`).
+/// - `profile`: Theme profile describing the theme's selectors (default: chosen from the theme name).
+/// - `[panel.N]`: Background color and opacity of one panel (commented out).
+/// - `[whisker]`: Whisker Menu appearance keys written to the rc files, with an example
+///   `[whisker."whiskermenu-5"]` override (commented out).
+/// - `[backup]`: Automatic snapshots before each update (default: enabled, keep `10`).
+///
+/// # Arguments
+/// - `explicit`: The path passed with `--config`, if any.
 ///
 /// # Returns
-/// - An `Ok(f32)` containing the search opacity value if found.
-/// - An `Err` if the `search_opacity` key is missing or not a valid float.
-pub fn get_search_opacity() -> Result<f32, Box<dyn Error>> {
-    let config: Value = load_config()?;
-
-    Ok(config["search_opacity"]
-        .as_float()
-        .ok_or("Search opacity not found in config")? as f32)
+/// - `Ok(())` if the configuration file is successfully created.
+/// - `Err(Box<dyn Error>)` if the file already exists or an error occurs during writing.
+pub fn create_default_config(explicit: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let config_path = match explicit {
+        Some(path) => path.to_path_buf(),
+        None => default_config_path()?,
+    };
+    if config_path.exists() {
+        return Err(format!("Config file '{}' already exists", config_path.display()).into());
+    }
+
+    let theme_path = match detect_theme() {
+        Ok(theme) => {
+            println!(
+                "Detected theme '{}': {}",
+                theme.name,
+                theme.css_path.display()
+            );
+            theme.css_path
+        }
+        Err(e) => {
+            println!("Could not detect the active theme ({e}); using the Mint-L-Dark default");
+            PathBuf::from(DEFAULT_THEME_PATH)
+        }
+    };
+    let theme_path = theme_path.display();
+
+    let config_content = format!(
+        r##"# default_paths
+# `~`, `$VAR` and `${{VAR}}` are expanded; under sudo `~` is the invoking user's home.
+theme_path = '{theme_path}'
+whisker_menu_path = '~/.config/xfce4/panel/'
+panel_path = '~/.config/xfce4/xfconf/xfce-perchannel-xml/xfce4-panel.xml'
+
+# colors: #RGB, #RGBA, #RRGGBB, #RRGGBBAA, rgb()/rgba(), hsl()/hsla(), CSS names like "navy",
+# or @name for a color the theme defines with @define-color; opacity multiplies the color's alpha.
+# Expressions derive colors from others: lighten(base, 8%), darken(base, 8%),
+# mix(base, accent, 30%), alpha(accent, 0.4); base, search, border and [colors] names can be used
+base_color = "#000000"
+opacity = 0.0
+search_color = "#000000"
+search_opacity = 0.0
+border_color = "base"
+
+# where the menu colors are written: "theme" patches theme_path (needs sudo for /usr/share/themes),
+# "user" writes scoped rules into ~/.config/gtk-3.0/gtk.css (no root, survives theme upgrades)
+backend = "theme"
+# user_css_path = "~/.config/gtk-3.0/gtk.css"
+
+# how panel settings and the theme of fork-theme --switch are written: "dbus" sets them live through
+# xfconfd, "file" edits panel_path and xsettings.xml (stop xfconfd first), "auto" uses D-Bus when available
+xfconf = "auto"
+
+# theme profile (mint-y, mint-l, greybird, arc, adwaita, materia, numix, generic or a user profile
+# from ~/.config/xfce4-transparent-whiskermenu/profiles); chosen from the theme name when not set
+# profile = "mint-l"
+
+# extra named colors for the expressions above
+# [colors]
+# accent = "#3daee9"
+
+# per-panel background (panel-N in xfce4-panel.xml); unset keys use base_color and opacity
+# [panel.2]
+# base_color = "#303030"
+# opacity = 0.8
+
+# Whisker Menu appearance, written to every whiskermenu-N.rc (unset keys are left alone;
+# menu_opacity defaults to opacity)
+# [whisker]
+# menu_width = 450
+# menu_height = 500
+# item_icon_size = 2
+# category_icon_size = 1
+# button_title = "Menu"
+# show_button_icon = true
+# position_search_alternate = false
+#
+# overrides for one plugin instance (see `list-whiskers` for the IDs)
+# [whisker."whiskermenu-5"]
+# opacity = 0.8
+# menu_width = 600
+
+# backups of every modified file, stored in ~/.local/state/xfce4-transparent-whiskermenu/backups
+[backup]
+enabled = true
+keep = 10"##
+    );
+
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&config_path, config_content)?;
+    println!("Created default config at {}", config_path.display());
+    Ok(())
 }
@@ -0,0 +1,255 @@
+use std::error::Error;
+use std::fs;
+
+use crate::color::Color;
+use crate::color_expr::ColorScope;
+use crate::color_updates::whisker_rc_files;
+use crate::config::Config;
+use crate::css::{define_colors, Stylesheet};
+use crate::profiles::{select_profile, ThemeProfile};
+use crate::wallpaper::{average_color, current_wallpaper};
+
+/// Minimum WCAG 2 contrast ratio of normal text for level AA.
+pub const WCAG_AA: f64 = 4.5;
+
+/// Minimum WCAG 2 contrast ratio of normal text for level AAA.
+pub const WCAG_AAA: f64 = 7.0;
+
+/// `@define-color` names GTK themes use for the default text color.
+const THEME_TEXT_COLORS: [&str; 2] = ["theme_fg_color", "fg_color"];
+
+/// What the semi-transparent menu is seen against.
+#[derive(Debug, Clone)]
+pub struct Backdrop {
+    pub color: Color,
+    pub description: String,
+}
+
+/// The contrast of one menu text color against the composited menu background.
+#[derive(Debug, Clone)]
+pub struct TextContrast {
+    /// Where the text color was read from, e.g. the rule selector.
+    pub source: String,
+    pub text: Color,
+    /// The menu background composited over the backdrop giving the lowest contrast.
+    pub background: Color,
+    /// The whisker menu instance whose `menu-opacity` gives the lowest contrast.
+    pub instance: String,
+    /// That `menu-opacity`, from 0 to 1.
+    pub opacity: f64,
+    pub backdrop: Backdrop,
+    pub ratio: f64,
+}
+
+/// Returns the backdrops to check against.
+///
+/// An explicit color is used as is. Otherwise the average color of the current wallpaper is
+/// used, and if there is none, both black and white so the worst case is reported.
+///
+/// # Errors
+/// Returns an error if the explicit backdrop is not a valid color expression.
+fn backdrops(config: &Config, explicit: Option<&str>) -> Result<Vec<Backdrop>, Box<dyn Error>> {
+    if let Some(expression) = explicit {
+        return Ok(vec![Backdrop {
+            color: config.color(expression)?.with_alpha(1.0),
+            description: "--backdrop".to_string(),
+        }]);
+    }
+    if let Ok((path, color)) = current_wallpaper().and_then(|path| {
+        let color = average_color(&path)?;
+        Ok((path, color))
+    }) {
+        return Ok(vec![Backdrop {
+            color,
+            description: format!("average of {}", path.display()),
+        }]);
+    }
+    Ok([
+        ("black", Color::from_rgb8(0, 0, 0, 1.0)),
+        ("white", Color::from_rgb8(255, 255, 255, 1.0)),
+    ]
+    .map(|(name, color)| Backdrop {
+        color,
+        description: format!("{name} backdrop (no wallpaper found)"),
+    })
+    .to_vec())
+}
+
+/// Lists the `menu-opacity` every whisker menu instance gets, from 0 to 1.
+///
+/// Each instance found in `whisker_menu_path` uses `menu_opacity` from its `[whisker]`
+/// section, the `[whisker]` defaults or `opacity`, as `--updatewhisker` writes it. Without
+/// any rc file the `[whisker]` defaults are used.
+fn menu_opacities(config: &Config) -> Vec<(String, f64)> {
+    let default = (config.opacity * 100.0).round() as u32;
+    let opacity = |id: &str| {
+        let settings = config.whisker.settings_for(id, default);
+        f64::from(settings.menu_opacity.unwrap_or(default)) / 100.0
+    };
+    let mut opacities: Vec<(String, f64)> = whisker_rc_files(&config.whisker_menu_path)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|path| path.file_stem()?.to_str())
+        .map(|id| (id.to_string(), opacity(id)))
+        .collect();
+    if opacities.is_empty() {
+        opacities.push(("[whisker]".to_string(), opacity("")));
+    }
+    opacities
+}
+
+/// Reads the menu text colors from the `color` declarations of the menu rules in the theme.
+///
+/// The rules are the ones the `menu_view` and `menu_background` targets of the profile
+/// update. If neither sets a color, the theme's `@theme_fg_color` (or `@fg_color`) is used.
+///
+/// # Errors
+/// Returns an error if the theme cannot be read or parsed, or no text color is found.
+fn menu_text_colors(
+    config: &Config,
+    profile: &ThemeProfile,
+) -> Result<Vec<(String, Color)>, Box<dyn Error>> {
+    let theme_path = &config.theme_path;
+    let content = fs::read_to_string(theme_path)
+        .map_err(|e| format!("Failed to read {}: {e}", theme_path.display()))?;
+    let stylesheet =
+        Stylesheet::parse(&content).map_err(|e| format!("{}: {e}", theme_path.display()))?;
+    let definitions =
+        define_colors(&content).map_err(|e| format!("{}: {e}", theme_path.display()))?;
+    let scope = ColorScope {
+        names: config.color_scope().names,
+        definitions: &definitions,
+    };
+
+    let mut colors = Vec::new();
+    for target in [&profile.targets.menu_view, &profile.targets.menu_background] {
+        let Some(selector) = target
+            .selectors
+            .iter()
+            .find(|selector| stylesheet.has_rule(selector))
+        else {
+            continue;
+        };
+        if let Some(value) = stylesheet.get_property(selector, "color") {
+            let color = scope
+                .evaluate(value)
+                .map_err(|e| format!("color of `{selector}`: {e}"))?;
+            colors.push((format!("`{selector}`"), color));
+        }
+    }
+    if colors.is_empty() {
+        if let Some(name) = THEME_TEXT_COLORS
+            .iter()
+            .find(|name| definitions.contains_key(**name))
+        {
+            colors.push((format!("@{name}"), scope.evaluate(&format!("@{name}"))?));
+        }
+    }
+    if colors.is_empty() {
+        return Err(format!("No menu text color found in {}", theme_path.display()).into());
+    }
+    Ok(colors)
+}
+
+/// Computes the contrast of the menu text against the menu background.
+///
+/// The background is `base_color` at the `menu-opacity` of each whisker menu instance,
+/// composited over each backdrop; the lowest contrast per text color is kept, together with
+/// the instance it occurs in. A translucent text color is composited as well.
+///
+/// # Arguments
+/// - `config`: The configuration providing the theme, base color and menu opacities.
+/// - `profile`: The theme profile naming the menu rules.
+/// - `backdrop`: A color expression to use as backdrop instead of the wallpaper.
+///
+/// # Errors
+/// Returns an error if the text or background color cannot be determined.
+pub fn check_contrast(
+    config: &Config,
+    profile: &ThemeProfile,
+    backdrop: Option<&str>,
+) -> Result<Vec<TextContrast>, Box<dyn Error>> {
+    let base = config.color(&config.base_color)?;
+    let opacities = menu_opacities(config);
+    let backdrops = backdrops(config, backdrop)?;
+
+    let mut results = Vec::new();
+    for (source, text) in menu_text_colors(config, profile)? {
+        let worst = opacities
+            .iter()
+            .flat_map(|opacity| backdrops.iter().map(move |backdrop| (opacity, backdrop)))
+            .map(|((instance, opacity), backdrop)| {
+                let background = base.with_opacity(*opacity).over(backdrop.color);
+                let ratio = text.over(background).contrast_ratio(background);
+                TextContrast {
+                    source: source.clone(),
+                    text,
+                    background,
+                    instance: instance.clone(),
+                    opacity: *opacity,
+                    backdrop: backdrop.clone(),
+                    ratio,
+                }
+            })
+            .min_by(|a, b| a.ratio.total_cmp(&b.ratio));
+        results.extend(worst);
+    }
+    Ok(results)
+}
+
+fn verdict(ratio: f64, minimum: f64) -> &'static str {
+    if ratio >= minimum {
+        "pass"
+    } else {
+        "fail"
+    }
+}
+
+/// Prints the contrast of the menu text for the `check-contrast` command.
+///
+/// # Returns
+/// - `Ok(true)` if every text color meets WCAG AA.
+/// - `Ok(false)` if one does not.
+/// - An `Err` if the colors cannot be determined.
+pub fn print_contrast(config: &Config, backdrop: Option<&str>) -> Result<bool, Box<dyn Error>> {
+    let profile = select_profile(config)?;
+    let results = check_contrast(config, &profile, backdrop)?;
+    for result in &results {
+        println!("Text {} from {}", result.text, result.source);
+        println!(
+            "  on {} ({} at menu-opacity {} of {} over {} {})",
+            result.background.with_alpha(1.0),
+            config.base_color,
+            (result.opacity * 100.0).round(),
+            result.instance,
+            result.backdrop.description,
+            result.backdrop.color,
+        );
+        println!("  contrast {:.2}:1", result.ratio);
+        println!("  AA  ({WCAG_AA:.1}:1): {}", verdict(result.ratio, WCAG_AA));
+        println!(
+            "  AAA ({WCAG_AAA:.1}:1): {}",
+            verdict(result.ratio, WCAG_AAA)
+        );
+    }
+    Ok(results.iter().all(|result| result.ratio >= WCAG_AA))
+}
+
+/// Prints a warning if the menu text falls below WCAG AA with the configured colors.
+///
+/// Nothing is printed when the colors cannot be determined; `check-contrast` reports why.
+pub fn warn_low_contrast(config: &Config, profile: &ThemeProfile) {
+    let Ok(results) = check_contrast(config, profile, None) else {
+        return;
+    };
+    if let Some(worst) = results
+        .iter()
+        .min_by(|a, b| a.ratio.total_cmp(&b.ratio))
+        .filter(|worst| worst.ratio < WCAG_AA)
+    {
+        println!(
+            "warning: menu text {} has a contrast of {:.2}:1 in {} over the {}, below WCAG AA ({WCAG_AA}:1); see `check-contrast`",
+            worst.source, worst.ratio, worst.instance, worst.backdrop.description
+        );
+    }
+}
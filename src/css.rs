@@ -0,0 +1,642 @@
+use std::error::Error;
+use std::ops::Range;
+
+use crate::color_expr::ColorDefinitions;
+
+/// A single `property: value` declaration inside a rule block.
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    /// Property name in lowercase.
+    pub name: String,
+    /// Byte range of the value, without surrounding whitespace.
+    pub value_span: Range<usize>,
+    /// Byte offset just after the declaration, including its `;` if present.
+    pub end: usize,
+    /// Whether the declaration is terminated by a `;`.
+    pub terminated: bool,
+}
+
+/// A qualified rule (`selectors { declarations }`).
+#[derive(Debug, Clone)]
+pub struct Rule {
+    /// The comma separated selectors, with comments removed and whitespace collapsed.
+    pub selectors: Vec<String>,
+    /// Byte range of the block contents, between `{` and `}`.
+    pub block_span: Range<usize>,
+    pub declarations: Vec<Declaration>,
+}
+
+/// A parsed stylesheet.
+///
+/// Only byte ranges into the source are stored, so edits made through
+/// [`Stylesheet::set_property`] leave every untouched byte of the file as it was.
+/// Rules nested in block at-rules such as `@media` are included in `rules`.
+#[derive(Debug, Clone)]
+pub struct Stylesheet<'a> {
+    source: &'a str,
+    pub rules: Vec<Rule>,
+}
+
+/// Walks the source byte by byte, skipping comments and strings.
+struct Tokenizer<'a> {
+    source: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(source: &'a str) -> Self {
+        Tokenizer {
+            source,
+            bytes: source.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn at_comment(&self) -> bool {
+        self.bytes[self.pos..].starts_with(b"/*")
+    }
+
+    fn skip_comment(&mut self) -> Result<(), Box<dyn Error>> {
+        let start = self.pos;
+        match self.source[self.pos + 2..].find("*/") {
+            Some(end) => {
+                self.pos += 2 + end + 2;
+                Ok(())
+            }
+            None => Err(format!("Unterminated comment at byte {start}").into()),
+        }
+    }
+
+    fn skip_string(&mut self, quote: u8) -> Result<(), Box<dyn Error>> {
+        let start = self.pos;
+        self.pos += 1;
+        while let Some(byte) = self.peek() {
+            self.pos += 1;
+            if byte == b'\\' {
+                self.pos += 1;
+            } else if byte == quote {
+                return Ok(());
+            }
+        }
+        Err(format!("Unterminated string at byte {start}").into())
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), Box<dyn Error>> {
+        while let Some(byte) = self.peek() {
+            if byte.is_ascii_whitespace() {
+                self.pos += 1;
+            } else if self.at_comment() {
+                self.skip_comment()?;
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Advances to the first of `stops` found outside comments, strings and parentheses.
+    ///
+    /// Returns the stop byte, or `None` at the end of the input.
+    fn skip_until(&mut self, stops: &[u8]) -> Result<Option<u8>, Box<dyn Error>> {
+        let mut depth = 0usize;
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'/' if self.at_comment() => {
+                    self.skip_comment()?;
+                    continue;
+                }
+                b'"' | b'\'' => {
+                    self.skip_string(byte)?;
+                    continue;
+                }
+                b'\\' => {
+                    // An escape at the very end has nothing to escape.
+                    self.pos = (self.pos + 2).min(self.source.len());
+                    continue;
+                }
+                b'(' | b'[' => depth += 1,
+                b')' | b']' => depth = depth.saturating_sub(1),
+                _ if depth == 0 && stops.contains(&byte) => return Ok(Some(byte)),
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        Ok(None)
+    }
+
+    /// Skips a `{ ... }` block starting at the current `{`, including nested blocks.
+    fn skip_block(&mut self) -> Result<(), Box<dyn Error>> {
+        let start = self.pos;
+        let mut depth = 0usize;
+        loop {
+            match self.skip_until(b"{}")? {
+                Some(b'{') => depth += 1,
+                Some(_) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.pos += 1;
+                        return Ok(());
+                    }
+                }
+                None => return Err(format!("Unclosed block starting at byte {start}").into()),
+            }
+            self.pos += 1;
+        }
+    }
+}
+
+/// Removes comments from a selector and collapses its whitespace.
+fn normalize_selector(selector: &str) -> String {
+    let mut without_comments = String::new();
+    let mut rest = selector;
+    while let Some(start) = rest.find("/*") {
+        without_comments.push_str(&rest[..start]);
+        without_comments.push(' ');
+        rest = rest[start + 2..]
+            .find("*/")
+            .map_or("", |end| &rest[start + 2 + end + 2..]);
+    }
+    without_comments.push_str(rest);
+    without_comments
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Splits a selector list on top-level commas and normalizes each selector.
+fn split_selectors(prelude: &str) -> Vec<String> {
+    let mut selectors = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (index, byte) in prelude.bytes().enumerate() {
+        match byte {
+            b'(' | b'[' => depth += 1,
+            b')' | b']' => depth = depth.saturating_sub(1),
+            b',' if depth == 0 => {
+                selectors.push(normalize_selector(&prelude[start..index]));
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    selectors.push(normalize_selector(&prelude[start..]));
+    selectors.retain(|s| !s.is_empty());
+    selectors
+}
+
+/// Trims ASCII whitespace from both ends of a byte range.
+fn trim_span(source: &str, span: Range<usize>) -> Range<usize> {
+    let text = &source[span.clone()];
+    let start = span.start + (text.len() - text.trim_start().len());
+    let end = span.end - (text.len() - text.trim_end().len());
+    start..end.max(start)
+}
+
+impl<'a> Stylesheet<'a> {
+    /// Parses a stylesheet.
+    ///
+    /// # Returns
+    /// - An `Ok(Stylesheet)` with every rule of the file.
+    /// - An `Err` if a comment, string or block is not closed.
+    pub fn parse(source: &'a str) -> Result<Self, Box<dyn Error>> {
+        let mut tokenizer = Tokenizer::new(source);
+        let mut rules = Vec::new();
+        Self::parse_rules(&mut tokenizer, &mut rules, false)?;
+        Ok(Stylesheet { source, rules })
+    }
+
+    /// Parses rules until the end of the input, or until the closing `}` if `nested`.
+    fn parse_rules(
+        tokenizer: &mut Tokenizer,
+        rules: &mut Vec<Rule>,
+        nested: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        loop {
+            tokenizer.skip_whitespace_and_comments()?;
+            let Some(byte) = tokenizer.peek() else {
+                return Ok(());
+            };
+
+            if byte == b'}' {
+                if nested {
+                    return Ok(());
+                }
+                // Stray closing brace; skip it like a browser would.
+                tokenizer.pos += 1;
+                continue;
+            }
+
+            let prelude_start = tokenizer.pos;
+            let stop = tokenizer.skip_until(b"{;}")?;
+
+            if byte == b'@' {
+                match stop {
+                    Some(b'{') => {
+                        let name = tokenizer.source[prelude_start..tokenizer.pos]
+                            .split_whitespace()
+                            .next()
+                            .unwrap_or_default()
+                            .to_ascii_lowercase();
+                        if matches!(name.as_str(), "@media" | "@supports") {
+                            tokenizer.pos += 1;
+                            Self::parse_rules(tokenizer, rules, true)?;
+                            if tokenizer.peek() == Some(b'}') {
+                                tokenizer.pos += 1;
+                            }
+                        } else {
+                            tokenizer.skip_block()?;
+                        }
+                    }
+                    Some(b';') => tokenizer.pos += 1,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match stop {
+                Some(b'{') => {
+                    let prelude = &tokenizer.source[prelude_start..tokenizer.pos];
+                    tokenizer.pos += 1;
+                    let block_start = tokenizer.pos;
+                    let declarations = Self::parse_declarations(tokenizer)?;
+                    rules.push(Rule {
+                        selectors: split_selectors(prelude),
+                        block_span: block_start..tokenizer.pos,
+                        declarations,
+                    });
+                    tokenizer.pos += 1;
+                }
+                // A prelude without a block is invalid CSS; drop it and carry on.
+                Some(b';') => tokenizer.pos += 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// Parses the declarations of a block, stopping at its closing `}`.
+    fn parse_declarations(tokenizer: &mut Tokenizer) -> Result<Vec<Declaration>, Box<dyn Error>> {
+        let block_start = tokenizer.pos;
+        let mut declarations = Vec::new();
+        loop {
+            tokenizer.skip_whitespace_and_comments()?;
+            let start = tokenizer.pos;
+            match tokenizer.skip_until(b":;{}")? {
+                None => return Err(format!("Unclosed block starting at byte {block_start}").into()),
+                Some(b'}') => return Ok(declarations),
+                Some(b';') => tokenizer.pos += 1,
+                Some(b'{') => tokenizer.skip_block()?,
+                Some(_) => {
+                    let name = normalize_selector(&tokenizer.source[start..tokenizer.pos]);
+                    tokenizer.pos += 1;
+                    let value_start = tokenizer.pos;
+                    let stop = tokenizer.skip_until(b";{}")?;
+                    match stop {
+                        None => {
+                            return Err(
+                                format!("Unclosed block starting at byte {block_start}").into()
+                            )
+                        }
+                        Some(b'{') => {
+                            // Nested block inside a declaration; not valid GTK CSS.
+                            tokenizer.skip_block()?;
+                            continue;
+                        }
+                        Some(_) => {}
+                    }
+                    let value_span = trim_span(tokenizer.source, value_start..tokenizer.pos);
+                    let terminated = stop == Some(b';');
+                    if terminated {
+                        tokenizer.pos += 1;
+                    }
+                    declarations.push(Declaration {
+                        name: name.to_ascii_lowercase(),
+                        value_span,
+                        end: if terminated {
+                            tokenizer.pos
+                        } else {
+                            trim_span(tokenizer.source, start..tokenizer.pos).end
+                        },
+                        terminated,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Returns the rules whose selector list contains every selector of `selector`.
+    ///
+    /// Selectors are compared after normalizing whitespace and removing comments, and the
+    /// order of a list does not matter: `entry` matches `spinbutton, entry`, and
+    /// `.view,iconview` matches a rule written as `iconview, /* x */ .view`.
+    pub fn find_rules(&self, selector: &str) -> Vec<&Rule> {
+        let wanted = split_selectors(selector);
+        if wanted.is_empty() {
+            return Vec::new();
+        }
+        self.rules
+            .iter()
+            .filter(|rule| wanted.iter().all(|w| rule.selectors.contains(w)))
+            .collect()
+    }
+
+    /// Returns `true` if at least one rule matches `selector` (see [`Stylesheet::find_rules`]).
+    pub fn has_rule(&self, selector: &str) -> bool {
+        !self.find_rules(selector).is_empty()
+    }
+
+    /// Returns the value of the last `property` declaration in the rules matching `selector`.
+    ///
+    /// An `!important` flag is not part of the returned value.
+    pub fn get_property(&self, selector: &str, property: &str) -> Option<&'a str> {
+        let property = property.to_ascii_lowercase();
+        let declaration = self
+            .find_rules(selector)
+            .into_iter()
+            .flat_map(|rule| &rule.declarations)
+            .rev()
+            .find(|declaration| declaration.name == property)?;
+        let value = &self.source[declaration.value_span.clone()];
+        // ASCII lowercasing keeps byte offsets, so the length is valid for `value` too.
+        Some(
+            match value.to_ascii_lowercase().strip_suffix("!important") {
+                Some(rest) => value[..rest.len()].trim_end(),
+                None => value,
+            },
+        )
+    }
+
+    /// Sets `property` to `value` in every rule matching `selector`.
+    ///
+    /// The last existing declaration of the property is rewritten in place, keeping an
+    /// `!important` flag. If a rule has no
+    /// such declaration, one is appended after its last declaration using the indentation of
+    /// the block. Everything else in the source is kept byte for byte.
+    ///
+    /// # Returns
+    /// - The updated source and the number of rules that were changed or inserted into;
+    ///   rules that already hold `value` are left alone and not counted.
+    pub fn set_property(&self, selector: &str, property: &str, value: &str) -> (String, usize) {
+        let property_lower = property.to_ascii_lowercase();
+        let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+
+        for rule in self.find_rules(selector) {
+            let existing = rule
+                .declarations
+                .iter()
+                .rev()
+                .find(|d| d.name == property_lower);
+            match existing {
+                Some(declaration) => {
+                    let current = &self.source[declaration.value_span.clone()];
+                    let important = current.to_ascii_lowercase().ends_with("!important");
+                    let value = if important {
+                        format!("{value} !important")
+                    } else {
+                        value.to_string()
+                    };
+                    if value != current {
+                        edits.push((declaration.value_span.clone(), value));
+                    }
+                }
+                None => edits.push(self.insertion(rule, property, value)),
+            }
+        }
+
+        let count = edits.len();
+        edits.sort_by_key(|(range, _)| range.start);
+        let mut updated = self.source.to_string();
+        for (range, text) in edits.into_iter().rev() {
+            updated.replace_range(range, &text);
+        }
+        (updated, count)
+    }
+
+    /// Builds the edit that appends a new declaration to a rule.
+    fn insertion(&self, rule: &Rule, property: &str, value: &str) -> (Range<usize>, String) {
+        let indent = rule
+            .declarations
+            .first()
+            .map(|d| {
+                let line_start = self.source[..d.value_span.start]
+                    .rfind('\n')
+                    .map_or(0, |i| i + 1);
+                let line = &self.source[line_start..];
+                line[..line.len() - line.trim_start().len()].to_string()
+            })
+            .unwrap_or_else(|| "  ".to_string());
+
+        match rule.declarations.last() {
+            Some(last) => {
+                let separator = if last.terminated { "" } else { ";" };
+                let same_line = !self.source[rule.block_span.start..last.end].contains('\n');
+                let text = if same_line {
+                    format!("{separator} {property}: {value};")
+                } else {
+                    format!("{separator}\n{indent}{property}: {value};")
+                };
+                (last.end..last.end, text)
+            }
+            None => {
+                let block = &self.source[rule.block_span.clone()];
+                let inner = block.trim();
+                if inner.is_empty() && !block.contains('\n') {
+                    (rule.block_span.clone(), format!(" {property}: {value}; "))
+                } else {
+                    let at = rule.block_span.start;
+                    (at..at, format!("\n{indent}{property}: {value};"))
+                }
+            }
+        }
+    }
+}
+
+/// Collects the top-level `@define-color name value;` rules of a GTK stylesheet.
+///
+/// Later definitions of a name replace earlier ones, as in GTK. Values are returned
+/// unparsed, with comments removed, since they may refer to other definitions.
+///
+/// # Errors
+/// Returns an error if a comment, string or block is not closed.
+pub fn define_colors(source: &str) -> Result<ColorDefinitions, Box<dyn Error>> {
+    let mut tokenizer = Tokenizer::new(source);
+    let mut definitions = ColorDefinitions::new();
+    loop {
+        tokenizer.skip_whitespace_and_comments()?;
+        if tokenizer.peek().is_none() {
+            return Ok(definitions);
+        }
+        let start = tokenizer.pos;
+        match tokenizer.skip_until(b"{;}")? {
+            Some(b'{') => tokenizer.skip_block()?,
+            Some(b';') => {
+                let prelude = normalize_selector(&source[start..tokenizer.pos]);
+                if let Some(rest) = prelude.strip_prefix("@define-color ") {
+                    if let Some((name, value)) = rest.split_once(' ') {
+                        definitions.insert(name.to_string(), value.to_string());
+                    }
+                }
+                tokenizer.pos += 1;
+            }
+            Some(_) => tokenizer.pos += 1,
+            None => return Ok(definitions),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(source: &str, selector: &str, property: &str, value: &str) -> (String, usize) {
+        Stylesheet::parse(source)
+            .unwrap()
+            .set_property(selector, property, value)
+    }
+
+    #[test]
+    fn round_trip_leaves_source_untouched() {
+        let source = "/* header */\n@import url(\"base.css\");\n\n.background {\n  color: #fff; /* x */\n  background-color: #000;\n}\n\n@media (min-width: 10px) { entry { color: red } }\n";
+        let sheet = Stylesheet::parse(source).unwrap();
+        assert_eq!(
+            sheet.set_property(".missing", "color", "red"),
+            (source.to_string(), 0)
+        );
+        assert_eq!(
+            sheet.set_property(".background", "background-color", "#000"),
+            (source.to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn edits_only_the_value() {
+        let source = ".background {\n  color: #fff;\n  background-color:  #000 ;\n}\n";
+        let (updated, count) = set(source, ".background", "background-color", "#123456");
+        assert_eq!(count, 1);
+        assert_eq!(
+            updated,
+            ".background {\n  color: #fff;\n  background-color:  #123456 ;\n}\n"
+        );
+    }
+
+    #[test]
+    fn reads_values_with_multibyte_characters() {
+        let sheet =
+            Stylesheet::parse("entry { content: \"€€€€\"; color: red !IMPORTANT; }").unwrap();
+        assert_eq!(sheet.get_property("entry", "content"), Some("\"€€€€\""));
+        assert_eq!(sheet.get_property("entry", "color"), Some("red"));
+    }
+
+    #[test]
+    fn keeps_important() {
+        let (updated, _) = set("entry { color: red !important; }", "entry", "color", "blue");
+        assert_eq!(updated, "entry { color: blue !important; }");
+    }
+
+    #[test]
+    fn skips_comments_and_strings() {
+        let source = "/* entry { color: red; } */\n.a::before { content: \"entry { color: red; }\"; }\nentry { /* color: green; */ color: blue; }\n";
+        let sheet = Stylesheet::parse(source).unwrap();
+        assert_eq!(sheet.rules.len(), 2);
+        assert_eq!(sheet.find_rules("entry").len(), 1);
+        assert_eq!(sheet.get_property("entry", "color"), Some("blue"));
+        let (updated, count) = sheet.set_property("entry", "color", "white");
+        assert_eq!(count, 1);
+        assert!(updated.ends_with("entry { /* color: green; */ color: white; }\n"));
+        assert!(updated.starts_with(
+            "/* entry { color: red; } */\n.a::before { content: \"entry { color: red; }\"; }"
+        ));
+    }
+
+    #[test]
+    fn finds_rules_inside_media() {
+        let source = "@media (prefers-dark) {\n  entry { color: red; }\n}\n@font-face { font-family: x; }\nentry { color: blue; }\n";
+        let sheet = Stylesheet::parse(source).unwrap();
+        assert_eq!(sheet.find_rules("entry").len(), 2);
+        let (updated, count) = sheet.set_property("entry", "color", "green");
+        assert_eq!(count, 2);
+        assert_eq!(
+            updated,
+            "@media (prefers-dark) {\n  entry { color: green; }\n}\n@font-face { font-family: x; }\nentry { color: green; }\n"
+        );
+    }
+
+    #[test]
+    fn updates_unterminated_last_declaration() {
+        let (updated, count) = set("entry { color: red }", "entry", "color", "blue");
+        assert_eq!((updated.as_str(), count), ("entry { color: blue }", 1));
+    }
+
+    #[test]
+    fn inserts_after_unterminated_last_declaration() {
+        let (updated, _) = set(
+            "entry {\n    color: red\n}\n",
+            "entry",
+            "border-color",
+            "blue",
+        );
+        assert_eq!(
+            updated,
+            "entry {\n    color: red;\n    border-color: blue;\n}\n"
+        );
+    }
+
+    #[test]
+    fn inserts_with_block_layout() {
+        let (one_line, _) = set("entry { color: red; }", "entry", "border-color", "blue");
+        assert_eq!(one_line, "entry { color: red; border-color: blue; }");
+
+        let (empty, _) = set("entry {}", "entry", "color", "blue");
+        assert_eq!(empty, "entry { color: blue; }");
+
+        let (multi_line, _) = set("entry {\n}\n", "entry", "color", "blue");
+        assert_eq!(multi_line, "entry {\n  color: blue;\n}\n");
+    }
+
+    #[test]
+    fn matches_selector_within_lists_in_any_order() {
+        let source = "entry, spinbutton { color: red; }\nspinbutton, entry { color: red; }\n.view, iconview { color: red; }\nentry:focus { color: red; }\n";
+        let sheet = Stylesheet::parse(source).unwrap();
+        assert_eq!(sheet.find_rules("entry").len(), 2);
+        assert_eq!(sheet.find_rules("iconview,.view").len(), 1);
+        assert_eq!(sheet.find_rules("iconview, /* x */ .view").len(), 1);
+        assert_eq!(sheet.find_rules(".view, iconview, textview").len(), 0);
+        assert!(!sheet.has_rule("spin"));
+    }
+
+    #[test]
+    fn reports_unterminated_input() {
+        assert!(Stylesheet::parse("entry { color: red;").is_err());
+        assert!(Stylesheet::parse("/* entry {}").is_err());
+        assert!(Stylesheet::parse("entry { content: \"x; }").is_err());
+    }
+
+    #[test]
+    fn reports_input_ending_inside_a_value() {
+        for source in ["entry{color:\\", "entry { color: red", "entry { color: \\€"] {
+            let error = Stylesheet::parse(source).unwrap_err().to_string();
+            assert!(error.contains("Unclosed block"), "{source}: {error}");
+        }
+        assert!(define_colors("@define-color fg \\").unwrap().is_empty());
+    }
+
+    #[test]
+    fn collects_define_colors() {
+        let definitions = define_colors(
+            "@define-color fg_color #eee;\n@define-color  bg_color /* dark */ @base;\n@media print { @define-color fg_color red; }\n@define-color fg_color #fff;\n",
+        )
+        .unwrap();
+        assert_eq!(
+            definitions.get("fg_color").map(String::as_str),
+            Some("#fff")
+        );
+        assert_eq!(
+            definitions.get("bg_color").map(String::as_str),
+            Some("@base")
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use regex::Regex;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::paths::{home_dir, xdg_config_home, xdg_data_home};
+use crate::xfconf::{Channel, XfconfValue};
+
+/// The active GTK theme as found in the xfconf `xsettings` channel.
+#[derive(Debug, Clone)]
+pub struct DetectedTheme {
+    /// Value of `Net/ThemeName`.
+    pub name: String,
+    /// The theme directory the name resolved to.
+    pub theme_dir: PathBuf,
+    /// The gtk-3.0 stylesheet to update.
+    pub css_path: PathBuf,
+    /// Why this stylesheet was chosen.
+    pub css_reason: String,
+}
+
+/// Returns the path of the xfconf `xsettings` channel file.
+pub fn xsettings_path() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(xdg_config_home()?
+        .join("xfce4")
+        .join("xfconf")
+        .join("xfce-perchannel-xml")
+        .join("xsettings.xml"))
+}
+
+/// Reads a string property of the `Net` group from `xsettings.xml`.
+fn read_net_property(xsettings: &str, name: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let channel = Channel::parse(xsettings)?;
+    Ok(match channel.property(&format!("/Net/{name}")) {
+        Some(property) => match property.value()? {
+            XfconfValue::String(value) => Some(value),
+            _ => None,
+        },
+        None => None,
+    })
+}
+
+/// Sets the value of an existing string property of the `Net` group in `xsettings.xml`.
+///
+/// # Returns
+/// - An `Ok(String)` with the updated file contents.
+/// - An `Err` if the file cannot be parsed or the `Net` group does not contain the property.
+pub fn set_net_property(
+    xsettings: &str,
+    name: &str,
+    value: &str,
+) -> Result<String, Box<dyn Error>> {
+    let mut channel = Channel::parse(xsettings)?;
+    let path = format!("/Net/{name}");
+    if channel.property(&path).is_none() {
+        return Err(format!("Net/{name} is not set in xsettings.xml").into());
+    }
+    channel.set(&path, &XfconfValue::String(value.to_string()))?;
+    Ok(channel.to_string())
+}
+
+/// Reads `Net/ThemeName` from an `xsettings.xml` file.
+///
+/// # Returns
+/// - An `Ok(String)` with the theme name.
+/// - An `Err` if the file cannot be read or does not set a theme name.
+pub fn read_theme_name(xsettings: &Path) -> Result<String, Box<dyn Error>> {
+    let content = fs::read_to_string(xsettings)
+        .map_err(|e| format!("Failed to read {}: {e}", xsettings.display()))?;
+    read_net_property(&content, "ThemeName")
+        .map_err(|e| format!("{}: {e}", xsettings.display()))?
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| format!("Net/ThemeName is not set in {}", xsettings.display()).into())
+}
+
+/// Returns the directories searched for themes, in the order GTK searches them.
+pub fn theme_search_dirs() -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    Ok(vec![
+        home_dir()?.join(".themes"),
+        xdg_data_home()?.join("themes"),
+        PathBuf::from("/usr/share/themes"),
+    ])
+}
+
+/// Returns the sibling stylesheet `gtk.css` imports, if `gtk.css` only consists of that import.
+fn imported_stylesheet(gtk_css: &Path) -> Option<PathBuf> {
+    let content = fs::read_to_string(gtk_css).ok()?;
+    let import_re = Regex::new(r#"^@import\s+url\(\s*["']?([^"')]+)["']?\s*\)\s*;$"#).ok()?;
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("/*"));
+    let line = lines.next()?;
+    if lines.next().is_some() {
+        return None;
+    }
+    let target = &import_re.captures(line)?[1];
+    if target.contains(':') || target.contains('/') {
+        return None;
+    }
+    let path = gtk_css.with_file_name(target);
+    path.is_file().then_some(path)
+}
+
+/// Chooses between `gtk.css` and `gtk-dark.css` in a theme's `gtk-3.0` directory.
+///
+/// A `gtk.css` that only imports another stylesheet is followed. Otherwise dark themes
+/// (by name) use `gtk-dark.css` when it exists and everything else uses `gtk.css`.
+fn choose_stylesheet(name: &str, gtk_dir: &Path) -> Option<(PathBuf, String)> {
+    let gtk_css = gtk_dir.join("gtk.css");
+    let gtk_dark_css = gtk_dir.join("gtk-dark.css");
+
+    if let Some(imported) = imported_stylesheet(&gtk_css) {
+        return Some((imported, "gtk.css only imports this stylesheet".to_string()));
+    }
+    if name.to_lowercase().contains("dark") && gtk_dark_css.is_file() {
+        return Some((gtk_dark_css, "dark theme with a gtk-dark.css".to_string()));
+    }
+    if gtk_css.is_file() {
+        return Some((gtk_css, "default stylesheet of the theme".to_string()));
+    }
+    gtk_dark_css
+        .is_file()
+        .then(|| (gtk_dark_css, "theme only ships gtk-dark.css".to_string()))
+}
+
+/// Detects the active GTK theme and the stylesheet to update.
+///
+/// # Returns
+/// - An `Ok(DetectedTheme)` describing the theme.
+/// - An `Err` if `xsettings.xml` cannot be read, or the theme has no gtk-3.0 stylesheet in
+///   any of the theme directories.
+pub fn detect_theme() -> Result<DetectedTheme, Box<dyn Error>> {
+    let name = read_theme_name(&xsettings_path()?)?;
+
+    for dir in theme_search_dirs()? {
+        let theme_dir = dir.join(&name);
+        if let Some((css_path, css_reason)) = choose_stylesheet(&name, &theme_dir.join("gtk-3.0")) {
+            return Ok(DetectedTheme {
+                name,
+                theme_dir,
+                css_path,
+                css_reason,
+            });
+        }
+    }
+
+    let searched: Vec<String> = theme_search_dirs()?
+        .iter()
+        .map(|dir| dir.display().to_string())
+        .collect();
+    Err(format!(
+        "Theme '{name}' has no gtk-3.0 stylesheet in {}",
+        searched.join(", ")
+    )
+    .into())
+}
+
+/// Prints the detected theme and how it was found.
+pub fn print_detected_theme() -> Result<(), Box<dyn Error>> {
+    let xsettings = xsettings_path()?;
+    println!("xsettings:  {}", xsettings.display());
+    let theme = detect_theme()?;
+    println!("theme:      {}", theme.name);
+    println!("directory:  {}", theme.theme_dir.display());
+    println!(
+        "stylesheet: {} ({})",
+        theme.css_path.display(),
+        theme.css_reason
+    );
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use regex::Regex;
+use std::error::Error;
+use std::fs;
+use std::os::unix::fs::{lchown, symlink};
+use std::path::{Path, PathBuf};
+
+use crate::changeset::Changeset;
+use crate::config::{set_config_key, Backend, Config};
+use crate::detect::{detect_theme, set_net_property, xsettings_path};
+use crate::paths::{sudo_owner, xdg_data_home};
+use crate::xfconf::XfconfValue;
+use crate::xfconf_dbus::connect_xfconf;
+
+/// Suffix appended to the name of the active theme to name its fork.
+pub const FORK_SUFFIX: &str = "-Transparent";
+
+/// Copies a theme directory recursively, keeping symlinks as symlinks.
+///
+/// Under `sudo` every copied entry is handed to the invoking user, so the fork can later be
+/// updated without root.
+fn copy_theme_dir(source: &Path, dest: &Path) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let from = entry.path();
+        let to = dest.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            symlink(fs::read_link(&from)?, &to)?;
+        } else if file_type.is_dir() {
+            copy_theme_dir(&from, &to)?;
+        } else {
+            fs::copy(&from, &to).map_err(|e| format!("Failed to copy {}: {e}", from.display()))?;
+        }
+        if let Some((uid, gid)) = sudo_owner() {
+            lchown(&to, Some(uid), Some(gid))?;
+        }
+    }
+    if let Some((uid, gid)) = sudo_owner() {
+        lchown(dest, Some(uid), Some(gid))?;
+    }
+    Ok(())
+}
+
+/// Renames the theme in the contents of an `index.theme` file.
+///
+/// Rewrites the `Name` and `GtkTheme` keys and leaves every other line as it is.
+fn rename_index_theme(content: &str, name: &str) -> Result<String, Box<dyn Error>> {
+    let key_re = Regex::new(r"(?m)^(\s*(?:Name|GtkTheme)\s*=\s*).*$")?;
+    Ok(key_re
+        .replace_all(content, |caps: &regex::Captures| {
+            format!("{}{name}", &caps[1])
+        })
+        .into_owned())
+}
+
+/// Forks the active theme into a private, writable copy and points the config at it.
+///
+/// The theme found in `xsettings.xml` is copied to `~/.local/share/themes/<Name>-Transparent`
+/// (or `name`), its `index.theme` is renamed and `theme_path` in the config file is set to the
+/// matching stylesheet of the copy. The original theme is never modified.
+///
+/// # Arguments
+/// - `config_path`: The config file whose `theme_path` is updated.
+/// - `name`: Name of the fork, defaulting to the active theme name with [`FORK_SUFFIX`].
+/// - `switch`: Also make the fork the active theme, through xfconfd when it is available
+///   (following the `xfconf` setting) and else in `xsettings.xml`.
+/// - `force`: Replace an existing fork of the same name.
+/// - `dry_run`: Only print what would be done.
+///
+/// # Errors
+/// Returns an error if the active theme cannot be detected, is already a fork, the fork
+/// exists without `force`, or copying or writing any file fails.
+pub fn fork_theme(
+    config_path: &Path,
+    name: Option<&str>,
+    switch: bool,
+    force: bool,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let config = Config::load(config_path)?;
+    let theme = detect_theme()?;
+    if theme.name.ends_with(FORK_SUFFIX) && name.is_none() {
+        return Err(format!(
+            "The active theme '{}' already is a fork; update it directly",
+            theme.name
+        )
+        .into());
+    }
+
+    let fork_name = name.map_or_else(|| format!("{}{FORK_SUFFIX}", theme.name), str::to_string);
+    if fork_name.is_empty() || fork_name.contains('/') || fork_name == theme.name {
+        return Err(format!("Invalid theme name '{fork_name}'").into());
+    }
+    let fork_dir = xdg_data_home()?.join("themes").join(&fork_name);
+    if fork_dir.exists() && !force {
+        return Err(format!(
+            "{} already exists; pass --force to replace it",
+            fork_dir.display()
+        )
+        .into());
+    }
+
+    // Keep the stylesheet the config already uses if it belongs to the active theme.
+    let relative_css = config
+        .theme_path
+        .strip_prefix(&theme.theme_dir)
+        .or_else(|_| theme.css_path.strip_prefix(&theme.theme_dir))?
+        .to_path_buf();
+    let fork_css: PathBuf = fork_dir.join(&relative_css);
+
+    let mut changes = Changeset::new();
+    let content = changes.read(config_path)?;
+    let content = set_config_key(
+        &content,
+        "theme_path",
+        &toml::Value::String(fork_css.to_string_lossy().into_owned()),
+    )?;
+    changes.stage(config_path, content);
+
+    let xsettings = xsettings_path()?;
+    let bus = if switch {
+        connect_xfconf(config.xfconf, &xsettings)?
+    } else {
+        None
+    };
+    if switch && bus.is_none() {
+        let content = changes.read(&xsettings)?;
+        let content = set_net_property(&content, "ThemeName", &fork_name)?;
+        changes.stage(&xsettings, content);
+    }
+
+    if dry_run {
+        println!(
+            "Would copy {} to {}",
+            theme.theme_dir.display(),
+            fork_dir.display()
+        );
+        changes.print_diff();
+        if bus.is_some() {
+            println!("Would set xsettings /Net/ThemeName to '{fork_name}' through xfconf");
+        }
+        return Ok(());
+    }
+
+    if fork_dir.exists() {
+        fs::remove_dir_all(&fork_dir)?;
+    }
+    if let Some(themes_dir) = fork_dir.parent() {
+        fs::create_dir_all(themes_dir)?;
+    }
+    copy_theme_dir(&theme.theme_dir, &fork_dir)?;
+    println!(
+        "Copied {} to {}",
+        theme.theme_dir.display(),
+        fork_dir.display()
+    );
+
+    let index_theme = fork_dir.join("index.theme");
+    if index_theme.is_file() {
+        let content = changes.read(&index_theme)?;
+        let content = rename_index_theme(&content, &fork_name)?;
+        changes.stage(&index_theme, content);
+    }
+    changes.commit()?;
+
+    println!("theme_path now points at {}", fork_css.display());
+    if let Some(bus) = &bus {
+        bus.set_property(
+            "xsettings",
+            "/Net/ThemeName",
+            &XfconfValue::String(fork_name.clone()),
+        )?;
+    }
+    if config.backend == Backend::User {
+        println!(
+            "Note: backend = \"user\" is set; switch to backend = \"theme\" to update the fork"
+        );
+    }
+    if bus.is_some() {
+        println!("Set xsettings /Net/ThemeName to '{fork_name}' through xfconf");
+    } else if switch {
+        println!(
+            "Set Net/ThemeName to '{fork_name}' in {}; log out and back in if xfsettingsd does not pick it up",
+            xsettings.display()
+        );
+    } else {
+        println!("Select '{fork_name}' in Appearance settings (or rerun with --switch) to use it");
+    }
+    Ok(())
+}
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::chown;
+use std::path::{Path, PathBuf};
+
+use crate::backup::sha256_hex;
+use crate::config::{Backend, Config};
+use crate::paths::{sudo_owner, sudo_user, xdg_state_home, APP_DIR_NAME};
+
+/// APT configuration snippet running the hook after every dpkg invocation.
+const APT_HOOK_PATH: &str = "/etc/apt/apt.conf.d/99xfce4-transparent-whiskermenu";
+
+/// pacman hook running after transactions that touch the theme stylesheet.
+const PACMAN_HOOK_PATH: &str = "/etc/pacman.d/hooks/xfce4-transparent-whiskermenu.hook";
+
+/// Package manager to install the re-apply hook for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PackageManager {
+    /// Debian, Ubuntu, Linux Mint: a `DPkg::Post-Invoke` snippet in `apt.conf.d`.
+    Apt,
+    /// Arch Linux: a `.hook` file in `/etc/pacman.d/hooks`.
+    Pacman,
+}
+
+impl PackageManager {
+    /// Picks the package manager of the running system.
+    fn detect() -> Result<Self, Box<dyn Error>> {
+        if Path::new("/etc/apt/apt.conf.d").is_dir() {
+            Ok(PackageManager::Apt)
+        } else if Path::new("/etc/pacman.conf").is_file() {
+            Ok(PackageManager::Pacman)
+        } else {
+            Err("No supported package manager found; pass --manager apt or --manager pacman".into())
+        }
+    }
+
+    fn hook_path(self) -> &'static Path {
+        Path::new(match self {
+            PackageManager::Apt => APT_HOOK_PATH,
+            PackageManager::Pacman => PACMAN_HOOK_PATH,
+        })
+    }
+}
+
+impl fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackageManager::Apt => write!(f, "APT"),
+            PackageManager::Pacman => write!(f, "pacman"),
+        }
+    }
+}
+
+/// Checksum of the theme stylesheet as the tool last wrote it.
+#[derive(Debug, Serialize, Deserialize)]
+struct AppliedState {
+    theme_path: PathBuf,
+    sha256: String,
+}
+
+/// Returns the file recording the last applied theme checksum.
+fn applied_state_path() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(xdg_state_home()?.join(APP_DIR_NAME).join("applied.toml"))
+}
+
+/// Records the checksum of the theme stylesheet after a successful update.
+///
+/// The hook compares against it to tell a package upgrade that reverted the stylesheet
+/// from a stylesheet that is still customized.
+///
+/// # Errors
+/// Returns an error if the stylesheet cannot be read or the state file cannot be written.
+pub fn record_applied(theme_path: &Path) -> Result<(), Box<dyn Error>> {
+    let state = AppliedState {
+        theme_path: theme_path.to_path_buf(),
+        sha256: sha256_hex(&fs::read(theme_path)?),
+    };
+    let path = applied_state_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, toml::to_string(&state)?)?;
+    if let Some((uid, gid)) = sudo_owner() {
+        chown(&path, Some(uid), Some(gid))?;
+    }
+    Ok(())
+}
+
+/// Checks whether the theme stylesheet changed since the tool last wrote it.
+///
+/// # Returns
+/// - `Ok(false)` with the `user` backend, which does not depend on package files, or if the
+///   checksum matches the recorded one.
+/// - `Ok(true)` if the checksum differs or nothing was recorded for this stylesheet.
+/// - An `Err` if the stylesheet cannot be read.
+pub fn theme_changed_since_apply(config: &Config) -> Result<bool, Box<dyn Error>> {
+    if config.backend == Backend::User {
+        return Ok(false);
+    }
+    let current = sha256_hex(&fs::read(&config.theme_path)?);
+    let recorded = fs::read_to_string(applied_state_path()?)
+        .ok()
+        .and_then(|content| toml::from_str::<AppliedState>(&content).ok())
+        .filter(|state| state.theme_path == config.theme_path);
+    Ok(recorded.is_none_or(|state| state.sha256 != current))
+}
+
+/// Quotes an argument for `sh` with single quotes.
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Builds the command the hook runs, acting for the user who installed it.
+fn hook_command(config_path: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let exe = std::env::current_exe()?.canonicalize()?;
+    let mut command = Vec::new();
+    if let Some(user) = sudo_user() {
+        command.push("/usr/bin/env".to_string());
+        command.push(format!("SUDO_USER={user}"));
+    }
+    command.push(exe.to_string_lossy().into_owned());
+    command.push("--config".to_string());
+    command.push(config_path.to_string_lossy().into_owned());
+    command.push("--no-reload".to_string());
+    command.push("run-hook".to_string());
+    Ok(command)
+}
+
+/// Renders the hook file for a package manager.
+fn render_hook(
+    manager: PackageManager,
+    command: &[String],
+    theme_path: &Path,
+) -> Result<String, Box<dyn Error>> {
+    let header =
+        "Installed by xfce4-transparent-whiskermenu install-hook; remove it with uninstall-hook.";
+    match manager {
+        PackageManager::Apt => {
+            let line: Vec<String> = command.iter().map(|arg| shell_quote(arg)).collect();
+            let line = line.join(" ");
+            if line.contains('"') {
+                return Err("Paths used by the APT hook must not contain '\"'".into());
+            }
+            Ok(format!(
+                "// {header}\nDPkg::Post-Invoke {{ \"{line} || true\"; }};\n"
+            ))
+        }
+        PackageManager::Pacman => {
+            if command.iter().any(|arg| arg.contains(char::is_whitespace)) {
+                return Err("Paths used by the pacman hook must not contain whitespace".into());
+            }
+            let target = theme_path.to_string_lossy();
+            Ok(format!(
+                "# {header}\n[Trigger]\nOperation = Install\nOperation = Upgrade\nType = Path\nTarget = {}\n\n\
+                 [Action]\nDescription = Re-applying the transparent Whisker Menu...\nWhen = PostTransaction\nExec = {}\n",
+                target.trim_start_matches('/'),
+                command.join(" ")
+            ))
+        }
+    }
+}
+
+/// Installs a package manager hook that re-patches the theme stylesheet after theme upgrades.
+///
+/// The hook runs `run-hook` with the given config, which only updates when the checksum
+/// of `theme_path` differs from the one recorded after the last update. It updates the
+/// stylesheet alone and never restarts the panel.
+///
+/// # Arguments
+/// - `config_path`: The config file the hook passes with `--config`.
+/// - `manager`: The package manager, detected when `None`.
+/// - `dry_run`: Only print the hook file.
+///
+/// # Errors
+/// Returns an error if the config is invalid, no package manager is found, or the hook
+/// cannot be written (usually because the command was not run with `sudo`).
+pub fn install_hook(
+    config_path: &Path,
+    manager: Option<PackageManager>,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let config = Config::load(config_path)?;
+    if config.backend == Backend::User {
+        println!(
+            "Note: backend = \"user\" is set; package upgrades do not revert the user stylesheet"
+        );
+    } else if !config.theme_path.starts_with("/usr") {
+        println!(
+            "Note: {} is not package-owned; upgrades will not revert it",
+            config.theme_path.display()
+        );
+    }
+
+    let manager = manager.map_or_else(PackageManager::detect, Ok)?;
+    let config_path = config_path.canonicalize()?;
+    let content = render_hook(manager, &hook_command(&config_path)?, &config.theme_path)?;
+    let hook_path = manager.hook_path();
+
+    if dry_run {
+        println!("Would write {}:\n{content}", hook_path.display());
+        return Ok(());
+    }
+    if let Some(dir) = hook_path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {e} (run with sudo)", dir.display()))?;
+    }
+    fs::write(hook_path, content).map_err(|e| {
+        format!(
+            "Failed to write {}: {e} (run with sudo)",
+            hook_path.display()
+        )
+    })?;
+    println!("Installed {manager} hook {}", hook_path.display());
+    Ok(())
+}
+
+/// Removes the hooks written by [`install_hook`].
+///
+/// # Errors
+/// Returns an error if an existing hook cannot be removed.
+pub fn uninstall_hook(dry_run: bool) -> Result<(), Box<dyn Error>> {
+    let mut removed = false;
+    for manager in [PackageManager::Apt, PackageManager::Pacman] {
+        let hook_path = manager.hook_path();
+        if !hook_path.exists() {
+            continue;
+        }
+        removed = true;
+        if dry_run {
+            println!("Would remove {manager} hook {}", hook_path.display());
+            continue;
+        }
+        fs::remove_file(hook_path).map_err(|e| {
+            format!(
+                "Failed to remove {}: {e} (run with sudo)",
+                hook_path.display()
+            )
+        })?;
+        println!("Removed {manager} hook {}", hook_path.display());
+    }
+    if !removed {
+        println!("No hook installed");
+    }
+    Ok(())
+}
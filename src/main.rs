@@ -1,18 +1,60 @@
+mod backup;
+mod changeset;
+mod color;
+mod color_expr;
 mod color_updates;
 mod config;
-mod regex_patterns;
-mod utils;
+mod contrast;
+mod css;
+mod detect;
+mod fork;
+mod hook;
+mod palette_import;
+mod panel_layout;
+mod paths;
+mod profiles;
+mod reload;
+mod user_css;
+mod wallpaper;
+mod whisker_rc;
+mod xfconf;
+mod xfconf_dbus;
 
-use clap::{CommandFactory, Parser};
+use clap::{CommandFactory, Parser, Subcommand};
 use std::error::Error;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-use crate::color_updates::{update_border, update_panel, update_search_bar, update_whiskar_menu};
-use crate::config::create_default_config;
+use crate::backup::{create_snapshot, print_snapshots, restore_snapshot};
+use crate::changeset::Changeset;
+use crate::color_updates::{
+    panel_properties, update_border, update_menu_colors, update_panel, update_search_bar,
+    update_whiskar_menu, UpdateReport,
+};
+use crate::config::{create_default_config, locate_config, print_config_path, Backend, Config};
+use crate::contrast::{print_contrast, warn_low_contrast};
+use crate::detect::print_detected_theme;
+use crate::fork::fork_theme;
+use crate::hook::{
+    install_hook, record_applied, theme_changed_since_apply, uninstall_hook, PackageManager,
+};
+use crate::palette_import::{import_scheme, SchemeFormat};
+use crate::panel_layout::print_whiskers;
+use crate::profiles::{print_profiles, select_profile, ThemeProfile};
+use crate::reload::reload_panel;
+use crate::wallpaper::from_wallpaper;
+use crate::xfconf::Channel;
+use crate::xfconf_dbus::connect_xfconf;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(name = "xfce4-transparent-whiskermenu")]
 struct Cli {
+    /// Path to the config file, overriding the XDG lookup
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
     #[arg(long)]
     updatepanel: bool,
 
@@ -30,40 +72,494 @@ struct Cli {
 
     #[arg(long)]
     updateborder: bool,
+
+    /// Print a diff of the pending changes instead of writing them
+    /// (exit code 2 if anything would change)
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Warn instead of failing when a theme pattern matches nothing
+    #[arg(long)]
+    lenient: bool,
+
+    /// Restart the panel after panel or rc files changed
+    /// (default when run from a terminal)
+    #[arg(long, global = true, conflicts_with = "no_reload")]
+    reload: bool,
+
+    /// Never restart the panel after applying
+    #[arg(long, global = true)]
+    no_reload: bool,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+impl Cli {
+    /// Whether to restart the panel after applying: `--reload`, `--no-reload`, or on when
+    /// run interactively from a terminal.
+    fn wants_reload(&self) -> bool {
+        self.reload || (!self.no_reload && std::io::stdin().is_terminal())
+    }
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Inspect the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Roll files back to a backup snapshot
+    Restore {
+        /// List the available snapshots
+        #[arg(long, conflicts_with_all = ["id", "last"])]
+        list: bool,
+
+        /// Restore the most recent snapshot
+        #[arg(long, conflicts_with = "id")]
+        last: bool,
+
+        /// Overwrite files even if they were edited after the update
+        #[arg(long)]
+        force: bool,
+
+        /// Identifier of the snapshot to restore
+        id: Option<String>,
+    },
+    /// List the theme profiles and show which one is selected
+    Profiles,
+    /// Print the active GTK theme found in xsettings.xml
+    Detect,
+    /// List the Whisker Menu plugin IDs with their panel and position
+    ListWhiskers,
+    /// Copy the active theme to ~/.local/share/themes and point theme_path at the copy
+    ForkTheme {
+        /// Name of the copy (default: the theme name with "-Transparent" appended)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Also make the copy the active theme in xsettings.xml
+        #[arg(long)]
+        switch: bool,
+
+        /// Replace an existing copy of the same name
+        #[arg(long)]
+        force: bool,
+    },
+    /// Set base_color and search_color from the colors of the desktop wallpaper
+    FromWallpaper {
+        /// Image to use instead of the wallpaper in xfce4-desktop.xml
+        #[arg(long)]
+        image: Option<PathBuf>,
+
+        /// Run --updateall with the new colors
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Check the contrast of the menu text against the menu background (WCAG AA/AAA)
+    CheckContrast {
+        /// Color behind the menu (default: the average color of the wallpaper)
+        #[arg(long)]
+        backdrop: Option<String>,
+    },
+    /// Set the config colors from a pywal, Xresources or base16 color scheme
+    Import {
+        /// Format of the scheme
+        #[arg(long, value_enum)]
+        from: SchemeFormat,
+
+        /// Scheme file (default: ~/.cache/wal/colors.json for pywal, ~/.Xresources for xresources)
+        file: Option<PathBuf>,
+
+        /// Run --updateall with the new colors
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Re-apply --updateall after package upgrades revert the theme (run with sudo)
+    InstallHook {
+        /// Package manager to install the hook for (default: detected)
+        #[arg(long, value_enum)]
+        manager: Option<PackageManager>,
+    },
+    /// Remove the package manager hook
+    UninstallHook,
+    /// Re-patch the theme stylesheet if it changed since the last update (used by the hook)
+    #[command(hide = true)]
+    RunHook,
 }
-fn main() -> Result<(), Box<dyn Error>> {
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print which config file is used and why
+    Path,
+}
+
+fn main() -> ExitCode {
     let cli = Cli::parse();
 
+    match run(&cli) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<ExitCode, Box<dyn Error>> {
+    if let Some(command) = &cli.command {
+        let result = match command {
+            Commands::RunHook => return run_hook(cli),
+            Commands::FromWallpaper { image, apply } => {
+                return edit_config_colors(cli, *apply, |path| {
+                    from_wallpaper(path, image.as_deref(), cli.dry_run)
+                });
+            }
+            Commands::Import { from, file, apply } => {
+                return edit_config_colors(cli, *apply, |path| {
+                    import_scheme(path, *from, file.as_deref(), cli.dry_run)
+                });
+            }
+            Commands::CheckContrast { backdrop } => {
+                let location = locate_config(cli.config.as_deref())?;
+                let config = Config::load(&location.path)?;
+                let passes = print_contrast(&config, backdrop.as_deref())?;
+                return Ok(if passes {
+                    ExitCode::SUCCESS
+                } else {
+                    ExitCode::FAILURE
+                });
+            }
+            Commands::Config {
+                action: ConfigAction::Path,
+            } => print_config_path(cli.config.as_deref()),
+            Commands::Restore { list: true, .. } => print_snapshots(),
+            Commands::Detect => print_detected_theme(),
+            Commands::ForkTheme {
+                name,
+                switch,
+                force,
+            } => locate_config(cli.config.as_deref()).and_then(|location| {
+                fork_theme(
+                    &location.path,
+                    name.as_deref(),
+                    *switch,
+                    *force,
+                    cli.dry_run,
+                )
+            }),
+            Commands::InstallHook { manager } => locate_config(cli.config.as_deref())
+                .and_then(|location| install_hook(&location.path, *manager, cli.dry_run)),
+            Commands::UninstallHook => uninstall_hook(cli.dry_run),
+            Commands::ListWhiskers => locate_config(cli.config.as_deref())
+                .and_then(|location| Config::load(&location.path))
+                .and_then(|config| print_whiskers(&config)),
+            Commands::Profiles => {
+                let config = locate_config(cli.config.as_deref())
+                    .ok()
+                    .map(|location| Config::load(&location.path))
+                    .transpose()?;
+                print_profiles(config.as_ref())
+            }
+            Commands::Restore {
+                last, force, id, ..
+            } => match (id, last) {
+                (Some(id), _) => restore_snapshot(Some(id), *force),
+                (None, true) => restore_snapshot(None, *force),
+                (None, false) => {
+                    Err("Specify a snapshot id or --last (see `restore --list`)".into())
+                }
+            },
+        };
+        return result.map(|()| ExitCode::SUCCESS);
+    }
+
     if !cli.updatepanel
         && !cli.updatewhisker
         && !cli.updatesearch
         && !cli.updateall
         && !cli.createconfig
+        && !cli.updateborder
     {
         Cli::command().print_help()?;
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
 
     if cli.createconfig {
-        create_default_config()?;
-        return Ok(());
+        create_default_config(cli.config.as_deref())?;
+        return Ok(ExitCode::SUCCESS);
     }
 
-    if cli.updateall || cli.updatewhisker {
-        update_whiskar_menu()?;
+    let location = locate_config(cli.config.as_deref())?;
+    let config = Config::load(&location.path)?;
+    apply_updates(
+        cli,
+        &config,
+        &UpdateSelection::from_cli(cli),
+        cli.wants_reload(),
+    )
+}
+
+/// Re-patches the theme stylesheet if it changed since the last update.
+///
+/// Only the stylesheet is updated: the panel and rc files are not package-owned, and the
+/// panel is never restarted from inside the package manager.
+fn run_hook(cli: &Cli) -> Result<ExitCode, Box<dyn Error>> {
+    let location = locate_config(cli.config.as_deref())?;
+    let config = Config::load(&location.path)?;
+    if !theme_changed_since_apply(&config)? {
+        println!("Theme unchanged since the last update");
+        return Ok(ExitCode::SUCCESS);
     }
+    apply_updates(cli, &config, &UpdateSelection::stylesheet(), false)
+}
 
-    if cli.updateall || cli.updatesearch {
-        update_search_bar()?;
+/// Rewrites the colors in the config file, then runs every update with them if `apply`.
+fn edit_config_colors(
+    cli: &Cli,
+    apply: bool,
+    edit: impl FnOnce(&Path) -> Result<(), Box<dyn Error>>,
+) -> Result<ExitCode, Box<dyn Error>> {
+    let location = locate_config(cli.config.as_deref())?;
+    edit(&location.path)?;
+    if !apply || cli.dry_run {
+        return Ok(ExitCode::SUCCESS);
     }
+    let config = Config::load(&location.path)?;
+    apply_updates(cli, &config, &UpdateSelection::all(), cli.wants_reload())
+}
 
-    if cli.updateall || cli.updatepanel {
-        update_panel()?;
+/// Stages the selected updates, then prints them (`--dry-run`) or backs up and writes them.
+///
+/// Panel properties that would be set through xfconfd are printed as `old -> new` in place
+/// of a diff of the panel file.
+///
+/// The panel is restarted afterwards if `reload` is set and panel or rc files changed.
+///
+/// # Returns
+/// - An `Ok(ExitCode)`: 2 for a dry run with pending changes, success otherwise.
+/// - An `Err` if staging fails, a required pattern matched nothing, or writing fails.
+fn apply_updates(
+    cli: &Cli,
+    config: &Config,
+    selection: &UpdateSelection,
+    reload: bool,
+) -> Result<ExitCode, Box<dyn Error>> {
+    let profile = select_profile(config)?;
+    match config.backend {
+        Backend::Theme => println!(
+            "Using theme profile '{}' ({})",
+            profile.name, profile.source
+        ),
+        Backend::User => println!("Using user stylesheet {}", config.user_css_path.display()),
     }
 
-    if cli.updateall || cli.updateborder {
-        update_border()?;
+    let mut changes = Changeset::new();
+    let report = selection.stage(config, &profile, &mut changes)?;
+    if selection.whisker {
+        warn_low_contrast(config, &profile);
     }
 
-    Ok(())
+    let live_panel = if selection.panel {
+        connect_xfconf(config.xfconf, &config.panel_path)?
+    } else {
+        None
+    };
+    // With xfconfd running, the panel properties are compared with and set to its live
+    // state; the panel file stays out of the changeset and the snapshot.
+    let property_changes = match &live_panel {
+        Some(bus) => {
+            let channel = match changes.take(&config.panel_path) {
+                Some((original, _)) => Channel::parse(&original)?,
+                None => Channel::parse(&std::fs::read_to_string(&config.panel_path)?)?,
+            };
+            bus.pending_changes("xfce4-panel", &panel_properties(config, &channel)?)?
+        }
+        None => Vec::new(),
+    };
+
+    if cli.dry_run {
+        changes.print_diff();
+        for change in &property_changes {
+            println!("would set {change}");
+        }
+        for unmatched in report.unmatched() {
+            println!("warning: {}", unmatched.describe_unmatched());
+        }
+        return Ok(if changes.has_changes() || !property_changes.is_empty() {
+            ExitCode::from(2)
+        } else {
+            println!("No changes");
+            ExitCode::SUCCESS
+        });
+    }
+
+    report.print_summary();
+    report.check(cli.lenient)?;
+
+    // The panel picks up properties set through xfconf by itself, but it reads the rc files
+    // and a panel file edited behind xfconfd's back only on start.
+    let needs_reload = changes
+        .changed_paths()
+        .iter()
+        .any(|path| *path == config.panel_path || path.starts_with(&config.whisker_menu_path));
+
+    let mut snapshot = if changes.has_changes() || !property_changes.is_empty() {
+        create_snapshot(&changes.changed_paths(), &property_changes, &config.backup)?
+    } else {
+        None
+    };
+    if let Some(snapshot) = &snapshot {
+        let mut saved = format!("{} file(s)", snapshot.manifest.files.len());
+        if !snapshot.manifest.xfconf.is_empty() {
+            saved.push_str(&format!(
+                " and {} xfconf propert{}",
+                snapshot.manifest.xfconf.len(),
+                if snapshot.manifest.xfconf.len() == 1 {
+                    "y"
+                } else {
+                    "ies"
+                }
+            ));
+        }
+        println!(
+            "Backed up {saved} to snapshot {} ({})",
+            snapshot.id,
+            snapshot.dir.display()
+        );
+    }
+
+    // Properties are set first so that they can be rolled back if the files cannot be
+    // written; the commit itself leaves no file modified when it fails.
+    if let Some(bus) = &live_panel {
+        if let Err(e) = bus.apply(&property_changes) {
+            if let Some(snapshot) = snapshot.take() {
+                snapshot.discard()?;
+            }
+            return Err(e);
+        }
+    }
+    if let Err(e) = changes.commit() {
+        let mut message = e.to_string();
+        if let Some(bus) = &live_panel {
+            let failed = bus.revert(&property_changes);
+            if !failed.is_empty() {
+                message.push_str(&format!(
+                    "; xfconf rollback failed for: {}",
+                    failed.join(", ")
+                ));
+            }
+        }
+        if let Some(snapshot) = snapshot.take() {
+            snapshot.discard()?;
+        }
+        return Err(message.into());
+    }
+    if let Some(snapshot) = &mut snapshot {
+        snapshot.record_written(config.backup.keep)?;
+    }
+    if live_panel.is_some() {
+        println!(
+            "Set {} xfce4-panel propert{} through xfconf",
+            property_changes.len(),
+            if property_changes.len() == 1 {
+                "y"
+            } else {
+                "ies"
+            }
+        );
+    }
+    if config.backend == Backend::Theme && selection.edits_stylesheet() {
+        record_applied(&config.theme_path)?;
+    }
+    if needs_reload && reload {
+        if let Err(e) = reload_panel() {
+            eprintln!("warning: {e}; run `xfce4-panel -r` to see the changes");
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The set of updates requested on the command line.
+struct UpdateSelection {
+    whisker: bool,
+    /// Whether `whisker` also covers the rc files, not only the menu colors in the stylesheet.
+    whisker_rc: bool,
+    search: bool,
+    panel: bool,
+    border: bool,
+}
+
+impl UpdateSelection {
+    fn from_cli(cli: &Cli) -> Self {
+        UpdateSelection {
+            whisker: cli.updateall || cli.updatewhisker,
+            whisker_rc: cli.updateall || cli.updatewhisker,
+            search: cli.updateall || cli.updatesearch,
+            panel: cli.updateall || cli.updatepanel,
+            border: cli.updateall || cli.updateborder,
+        }
+    }
+
+    /// Selects every update, like `--updateall`.
+    fn all() -> Self {
+        UpdateSelection {
+            whisker: true,
+            whisker_rc: true,
+            search: true,
+            panel: true,
+            border: true,
+        }
+    }
+
+    /// Selects only the stylesheet updates, which a theme package upgrade reverts.
+    fn stylesheet() -> Self {
+        UpdateSelection {
+            whisker: true,
+            whisker_rc: false,
+            search: true,
+            panel: false,
+            border: true,
+        }
+    }
+
+    /// Whether the selected updates edit the stylesheet (the theme or the user CSS).
+    fn edits_stylesheet(&self) -> bool {
+        self.whisker || self.search || self.border
+    }
+
+    /// Runs the selected update functions in order, staging their results in `changes`.
+    ///
+    /// # Returns
+    /// - An `Ok(UpdateReport)` combining the match and change counts of every update.
+    /// - An `Err` if any update fails.
+    fn stage(
+        &self,
+        config: &Config,
+        profile: &ThemeProfile,
+        changes: &mut Changeset,
+    ) -> Result<UpdateReport, Box<dyn Error>> {
+        let mut report = UpdateReport::default();
+
+        if self.whisker_rc {
+            report.merge(update_whiskar_menu(config, profile, changes)?);
+        } else if self.whisker {
+            report.merge(update_menu_colors(config, profile, changes)?);
+        }
+
+        if self.search {
+            report.merge(update_search_bar(config, profile, changes)?);
+        }
+
+        if self.panel {
+            report.merge(update_panel(config, changes)?);
+        }
+
+        if self.border {
+            report.merge(update_border(config, profile, changes)?);
+        }
+
+        Ok(report)
+    }
 }
@@ -0,0 +1,283 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::color::Color;
+use crate::config::update_config_keys;
+use crate::paths::home_dir;
+
+/// Color scheme formats that `import` reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SchemeFormat {
+    /// `colors.json` written by pywal (default: `~/.cache/wal/colors.json`).
+    Pywal,
+    /// `*background` and `*color0`..`*color15` resources (default: `~/.Xresources`).
+    Xresources,
+    /// A base16 scheme (`base00`..`base0F`) in YAML.
+    Base16,
+}
+
+/// The colors of a scheme the config is built from.
+#[derive(Debug, Clone, Copy)]
+pub struct Scheme {
+    /// Background of the scheme; becomes `base_color`.
+    pub background: Color,
+    /// A slightly lighter background (bright black, base16 `base02`); becomes `search_color`.
+    pub surface: Color,
+    /// Accent (blue, base16 `base0D`); becomes `[colors] accent`, used by `border_color`.
+    pub accent: Color,
+}
+
+/// The part of pywal's `colors.json` that is used.
+#[derive(Debug, Deserialize)]
+struct WalColors {
+    special: WalSpecial,
+    colors: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WalSpecial {
+    background: String,
+}
+
+impl SchemeFormat {
+    /// Returns the file read when none is given, if the format has one.
+    fn default_path(self) -> Result<Option<PathBuf>, Box<dyn Error>> {
+        Ok(match self {
+            SchemeFormat::Pywal => Some(home_dir()?.join(".cache/wal/colors.json")),
+            SchemeFormat::Xresources => Some(home_dir()?.join(".Xresources")),
+            SchemeFormat::Base16 => None,
+        })
+    }
+
+    /// Parses the contents of a scheme file.
+    ///
+    /// # Errors
+    /// Returns an error if the file is malformed or a needed color is missing or invalid.
+    fn parse(self, content: &str) -> Result<Scheme, Box<dyn Error>> {
+        match self {
+            SchemeFormat::Pywal => parse_pywal(content),
+            SchemeFormat::Xresources => parse_xresources(content),
+            SchemeFormat::Base16 => parse_base16(content),
+        }
+    }
+}
+
+/// Parses a color of a scheme, naming the key it came from in errors.
+fn scheme_color(colors: &BTreeMap<String, String>, key: &str) -> Result<Color, Box<dyn Error>> {
+    let value = colors
+        .get(key)
+        .ok_or_else(|| format!("The scheme has no {key}"))?;
+    Color::parse(value).map_err(|e| format!("{key}: {e}").into())
+}
+
+fn parse_pywal(content: &str) -> Result<Scheme, Box<dyn Error>> {
+    let wal: WalColors =
+        serde_json::from_str(content).map_err(|e| format!("Invalid colors.json: {e}"))?;
+    Ok(Scheme {
+        background: Color::parse(&wal.special.background)
+            .map_err(|e| format!("special.background: {e}"))?,
+        surface: scheme_color(&wal.colors, "color8")?,
+        accent: scheme_color(&wal.colors, "color4")?,
+    })
+}
+
+/// Parses X resources, using `*name` and `*.name` entries that apply to every program.
+///
+/// `!` comments are skipped and values may use names from `#define` lines, as the base16
+/// Xresources templates do. A later entry of a resource replaces an earlier one.
+fn parse_xresources(content: &str) -> Result<Scheme, Box<dyn Error>> {
+    let mut defines: BTreeMap<&str, &str> = BTreeMap::new();
+    let mut resources = BTreeMap::new();
+    for line in content.lines().map(str::trim) {
+        if let Some(define) = line.strip_prefix("#define") {
+            let mut parts = define.split_whitespace();
+            if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+                defines.insert(name, value);
+            }
+            continue;
+        }
+        if line.starts_with('!') || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let Some(name) = key
+            .trim()
+            .strip_prefix("*.")
+            .or_else(|| key.trim().strip_prefix('*'))
+        else {
+            continue;
+        };
+        let value = value.trim();
+        let value = defines.get(value).copied().unwrap_or(value);
+        // base16 templates define bare hex digits.
+        let value = if value.len() == 6 && value.bytes().all(|b| b.is_ascii_hexdigit()) {
+            format!("#{value}")
+        } else {
+            value.to_string()
+        };
+        resources.insert(name.to_string(), value);
+    }
+    Ok(Scheme {
+        background: scheme_color(&resources, "background")?,
+        surface: scheme_color(&resources, "color8")?,
+        accent: scheme_color(&resources, "color4")?,
+    })
+}
+
+/// Parses the `baseXX: "hex"` lines of a base16 scheme, flat or nested under `palette:`.
+fn parse_base16(content: &str) -> Result<Scheme, Box<dyn Error>> {
+    let mut colors = BTreeMap::new();
+    for line in content.lines() {
+        let line = line.split_once(" #").map_or(line, |(line, _)| line).trim();
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().trim_matches(['"', '\'']);
+        if key.len() != 6 || !key.to_ascii_lowercase().starts_with("base") {
+            continue;
+        }
+        let value = value.trim().trim_matches(['"', '\'']);
+        let value = value.strip_prefix('#').unwrap_or(value);
+        colors.insert(key.to_ascii_lowercase(), format!("#{value}"));
+    }
+    Ok(Scheme {
+        background: scheme_color(&colors, "base00")?,
+        surface: scheme_color(&colors, "base02")?,
+        accent: scheme_color(&colors, "base0d")?,
+    })
+}
+
+/// Sets the config colors from a pywal, Xresources or base16 scheme.
+///
+/// The scheme background becomes `base_color`, its lighter background `search_color` and
+/// its accent `[colors] accent`, with `border_color` set to `accent`. Opacities are kept.
+///
+/// # Arguments
+/// - `config_path`: The config file to update.
+/// - `format`: The format of the scheme.
+/// - `file`: The scheme file, defaulting to the usual location for pywal and Xresources.
+/// - `dry_run`: Only print the changes to the config file.
+///
+/// # Errors
+/// Returns an error if the scheme cannot be read or parsed, or the config file cannot be
+/// read or written.
+pub fn import_scheme(
+    config_path: &Path,
+    format: SchemeFormat,
+    file: Option<&Path>,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let file = match file {
+        Some(file) => file.to_path_buf(),
+        None => format
+            .default_path()?
+            .ok_or("base16 needs the scheme file, e.g. `import --from base16 scheme.yaml`")?,
+    };
+    let content =
+        fs::read_to_string(&file).map_err(|e| format!("Failed to read {}: {e}", file.display()))?;
+    let scheme = format
+        .parse(&content)
+        .map_err(|e| format!("{}: {e}", file.display()))?;
+    println!("Scheme: {}", file.display());
+    println!("  background: {}", scheme.background);
+    println!("  surface:    {}", scheme.surface);
+    println!("  accent:     {}", scheme.accent);
+
+    update_config_keys(
+        config_path,
+        &[
+            (
+                "base_color",
+                toml::Value::String(scheme.background.to_css()),
+            ),
+            ("search_color", toml::Value::String(scheme.surface.to_css())),
+            ("border_color", toml::Value::String("accent".to_string())),
+            ("colors.accent", toml::Value::String(scheme.accent.to_css())),
+        ],
+        dry_run,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the background, surface and accent of a scheme as CSS.
+    fn colors(scheme: Scheme) -> [String; 3] {
+        [scheme.background, scheme.surface, scheme.accent].map(Color::to_css)
+    }
+
+    #[test]
+    fn parses_pywal_colors() {
+        let content = r##"{
+            "wallpaper": "/home/user/wall.png",
+            "special": { "background": "#1d1f21", "foreground": "#c5c8c6", "cursor": "#c5c8c6" },
+            "colors": {
+                "color0": "#1d1f21", "color4": "#81a2be", "color8": "#373b41", "color15": "#ffffff"
+            }
+        }"##;
+        assert_eq!(
+            colors(parse_pywal(content).unwrap()),
+            ["#1d1f21", "#373b41", "#81a2be"]
+        );
+    }
+
+    #[test]
+    fn parses_xresources_with_defines_and_both_wildcards() {
+        let content = "\
+! base16 template
+#define base00 1d1f21
+#define base0D 81a2be
+*.background: base00
+*color8: #373b41
+*.color4: #ff0000
+URxvt*color4: #00ff00
+*.color4: base0D
+";
+        assert_eq!(
+            colors(parse_xresources(content).unwrap()),
+            ["#1d1f21", "#373b41", "#81a2be"]
+        );
+    }
+
+    #[test]
+    fn parses_base16_with_quotes_and_comments() {
+        let content = "\
+scheme: \"Tomorrow Night\"
+author: \"Chris Kempson\"
+palette:
+  \"base00\": \"1d1f21\" # background
+  'base02': '#373b41'
+  base0D: 81a2be # blue
+  base0E: b294bb
+";
+        assert_eq!(
+            colors(parse_base16(content).unwrap()),
+            ["#1d1f21", "#373b41", "#81a2be"]
+        );
+    }
+
+    #[test]
+    fn reports_missing_colors() {
+        let pywal =
+            r##"{ "special": { "background": "#000000" }, "colors": { "color8": "#111111" } }"##;
+        let xresources = "*background: #000000\n*color4: #0000ff\n";
+        let base16 = "base00: \"000000\"\nbase0D: \"0000ff\"\n";
+        for (error, key) in [
+            (parse_pywal(pywal).unwrap_err(), "color4"),
+            (parse_xresources(xresources).unwrap_err(), "color8"),
+            (parse_base16(base16).unwrap_err(), "base02"),
+        ] {
+            assert_eq!(error.to_string(), format!("The scheme has no {key}"));
+        }
+        assert!(parse_base16("base00: \"zzzzzz\"")
+            .unwrap_err()
+            .to_string()
+            .starts_with("base00: "));
+    }
+}
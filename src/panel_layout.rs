@@ -0,0 +1,138 @@
+use std::error::Error;
+use std::fs;
+
+use crate::config::Config;
+use crate::xfconf::{Channel, XfconfValue};
+
+/// A panel plugin as registered in `xfce4-panel.xml`.
+#[derive(Debug, Clone)]
+pub struct PanelPlugin {
+    /// The plugin ID `N` of `plugin-N`.
+    pub id: u32,
+    /// The plugin type, e.g. `whiskermenu`.
+    pub kind: String,
+    /// The panel ID `N` of `panel-N` the plugin is placed on.
+    pub panel: Option<u32>,
+    /// The 1-based position of the plugin on its panel and the number of plugins there.
+    pub position: Option<(usize, usize)>,
+}
+
+/// Reads the plugins and their placement from the contents of `xfce4-panel.xml`.
+///
+/// Plugins are listed under `/plugins/plugin-N` with their type as value, and each panel
+/// lists its plugins in order in `/panels/panel-N/plugin-ids`.
+///
+/// # Returns
+/// - An `Ok(Vec<PanelPlugin>)` sorted by plugin ID.
+/// - An `Err` if the file cannot be parsed.
+pub fn read_plugins(panel_xml: &str) -> Result<Vec<PanelPlugin>, Box<dyn Error>> {
+    let channel = Channel::parse(panel_xml)?;
+
+    let mut plugins = Vec::new();
+    for property in channel
+        .property("/plugins")
+        .map_or(&[][..], |p| &p.properties)
+    {
+        let Some(id) = numbered(&property.name, "plugin-") else {
+            continue;
+        };
+        if let XfconfValue::String(kind) = property.value()? {
+            plugins.push(PanelPlugin {
+                id,
+                kind,
+                panel: None,
+                position: None,
+            });
+        }
+    }
+
+    for panel in channel
+        .property("/panels")
+        .map_or(&[][..], |p| &p.properties)
+    {
+        let Some(panel_id) = numbered(&panel.name, "panel-") else {
+            continue;
+        };
+        let Some(XfconfValue::Array(ids)) = panel
+            .child("plugin-ids")
+            .map(|ids| ids.value())
+            .transpose()?
+        else {
+            continue;
+        };
+        for (position, id) in ids.iter().enumerate() {
+            let id = match id {
+                XfconfValue::Int(id) => u32::try_from(*id).ok(),
+                XfconfValue::Uint(id) => Some(*id),
+                _ => None,
+            };
+            if let Some(plugin) = plugins.iter_mut().find(|plugin| Some(plugin.id) == id) {
+                plugin.panel = Some(panel_id);
+                plugin.position = Some((position + 1, ids.len()));
+            }
+        }
+    }
+
+    plugins.sort_by_key(|plugin| plugin.id);
+    Ok(plugins)
+}
+
+/// Parses the number of a property named like `panel-1` or `plugin-5`.
+pub fn numbered(name: &str, prefix: &str) -> Option<u32> {
+    name.strip_prefix(prefix)?.parse().ok()
+}
+
+/// Prints every Whisker Menu plugin instance with its panel, position and rc file.
+///
+/// # Arguments
+/// - `config`: The loaded configuration providing the panel file and the rc directory.
+///
+/// # Errors
+/// Returns an error if `xfce4-panel.xml` cannot be read.
+pub fn print_whiskers(config: &Config) -> Result<(), Box<dyn Error>> {
+    let content = fs::read_to_string(&config.panel_path)
+        .map_err(|e| format!("Failed to read {}: {e}", config.panel_path.display()))?;
+    let whiskers: Vec<PanelPlugin> = read_plugins(&content)
+        .map_err(|e| format!("{}: {e}", config.panel_path.display()))?
+        .into_iter()
+        .filter(|plugin| plugin.kind == "whiskermenu")
+        .collect();
+    if whiskers.is_empty() {
+        println!("No Whisker Menu plugins in {}", config.panel_path.display());
+    } else {
+        print_whisker_table(config, &whiskers);
+    }
+    for id in config.whisker.missing_instances(&config.whisker_menu_path) {
+        println!("warning: [whisker.\"{id}\"] is configured but has no rc file");
+    }
+    Ok(())
+}
+
+/// Prints one row per Whisker Menu plugin.
+fn print_whisker_table(config: &Config, whiskers: &[PanelPlugin]) {
+    println!(
+        "{:<16} {:<10} {:<10} {:<10} RC FILE",
+        "ID", "PANEL", "POSITION", "CONFIG"
+    );
+    for plugin in whiskers {
+        let id = format!("whiskermenu-{}", plugin.id);
+        let panel = plugin
+            .panel
+            .map_or_else(|| "-".to_string(), |panel| format!("panel-{panel}"));
+        let position = plugin.position.map_or_else(
+            || "-".to_string(),
+            |(index, count)| format!("{index} of {count}"),
+        );
+        let overrides = if config.whisker.instances.contains_key(&id) {
+            "override"
+        } else {
+            "defaults"
+        };
+        let rc_path = config.whisker_menu_path.join(format!("{id}.rc"));
+        let rc_state = if rc_path.is_file() { "" } else { " (missing)" };
+        println!(
+            "{id:<16} {panel:<10} {position:<10} {overrides:<10} {}{rc_state}",
+            rc_path.display()
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+
+/// Directory name used below the XDG configuration and state directories.
+pub const APP_DIR_NAME: &str = "xfce4-transparent-whiskermenu";
+
+/// Returns the user id of the running process.
+fn current_uid() -> Option<u32> {
+    fs::metadata("/proc/self").ok().map(|m| m.uid())
+}
+
+/// A single entry of `/etc/passwd`.
+struct PasswdEntry {
+    uid: u32,
+    gid: u32,
+    home: PathBuf,
+}
+
+/// Looks up an entry in `/etc/passwd`, matching either the user name or the uid.
+fn passwd_entry(matches: impl Fn(&str, &str) -> bool) -> Option<PasswdEntry> {
+    let passwd = fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() >= 6 && matches(fields[0], fields[2]) && !fields[5].is_empty() {
+            Some(PasswdEntry {
+                uid: fields[2].parse().ok()?,
+                gid: fields[3].parse().ok()?,
+                home: PathBuf::from(fields[5]),
+            })
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns the name of the user that invoked `sudo`, if the process runs as root through `sudo`.
+pub fn sudo_user() -> Option<String> {
+    let user = env::var("SUDO_USER")
+        .ok()
+        .filter(|u| !u.is_empty() && u != "root")?;
+    (current_uid() == Some(0)).then_some(user)
+}
+
+/// Returns the uid and gid of the user that invoked `sudo`.
+///
+/// Files the tool creates in that user's home (backups, state) are handed back to them
+/// with these ids so they stay manageable without root.
+pub fn sudo_owner() -> Option<(u32, u32)> {
+    let user = sudo_user()?;
+    passwd_entry(|name, _| name == user).map(|entry| (entry.uid, entry.gid))
+}
+
+/// Returns the home directory of the user the tool acts for.
+///
+/// Under `sudo` this is the home of `SUDO_USER` rather than root's. Otherwise `$HOME` is
+/// used, falling back to the passwd entry of the current user.
+///
+/// # Returns
+/// - An `Ok(PathBuf)` with the home directory.
+/// - An `Err` if no home directory can be determined.
+pub fn home_dir() -> Result<PathBuf, Box<dyn Error>> {
+    if let Some(user) = sudo_user() {
+        if let Some(entry) = passwd_entry(|name, _| name == user) {
+            return Ok(entry.home);
+        }
+    }
+    if let Some(home) = env::var_os("HOME").filter(|h| !h.is_empty()) {
+        return Ok(PathBuf::from(home));
+    }
+    let uid = current_uid().map(|uid| uid.to_string());
+    uid.and_then(|uid| passwd_entry(|_, id| id == uid))
+        .map(|entry| entry.home)
+        .ok_or_else(|| "Cannot determine the home directory: HOME is not set".into())
+}
+
+/// Returns the user configuration directory (`$XDG_CONFIG_HOME`, defaulting to `~/.config`).
+///
+/// Under `sudo` the variable of the root environment is ignored and the invoking user's
+/// `~/.config` is returned.
+pub fn xdg_config_home() -> Result<PathBuf, Box<dyn Error>> {
+    if sudo_user().is_none() {
+        if let Some(dir) = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+        {
+            return Ok(dir);
+        }
+    }
+    Ok(home_dir()?.join(".config"))
+}
+
+/// Returns the user state directory (`$XDG_STATE_HOME`, defaulting to `~/.local/state`).
+///
+/// Like [`xdg_config_home`], the root environment is ignored under `sudo`.
+pub fn xdg_state_home() -> Result<PathBuf, Box<dyn Error>> {
+    if sudo_user().is_none() {
+        if let Some(dir) = env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+        {
+            return Ok(dir);
+        }
+    }
+    Ok(home_dir()?.join(".local").join("state"))
+}
+
+/// Returns the user data directory (`$XDG_DATA_HOME`, defaulting to `~/.local/share`).
+///
+/// Like [`xdg_config_home`], the root environment is ignored under `sudo`.
+pub fn xdg_data_home() -> Result<PathBuf, Box<dyn Error>> {
+    if sudo_user().is_none() {
+        if let Some(dir) = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+        {
+            return Ok(dir);
+        }
+    }
+    Ok(home_dir()?.join(".local").join("share"))
+}
+
+/// Returns the system configuration directories (`$XDG_CONFIG_DIRS`, defaulting to `/etc/xdg`).
+pub fn xdg_config_dirs() -> Vec<PathBuf> {
+    let dirs = env::var("XDG_CONFIG_DIRS").unwrap_or_default();
+    let dirs: Vec<PathBuf> = env::split_paths(&dirs)
+        .filter(|p| p.is_absolute())
+        .collect();
+    if dirs.is_empty() {
+        vec![PathBuf::from("/etc/xdg")]
+    } else {
+        dirs
+    }
+}
+
+/// Resolves the value of a variable referenced in a configured path.
+///
+/// `HOME` and `XDG_CONFIG_HOME` go through [`home_dir`] and [`xdg_config_home`] so that they
+/// follow the invoking user under `sudo` and have their usual defaults when unset.
+fn lookup_var(name: &str) -> Result<String, Box<dyn Error>> {
+    let value = match name {
+        "HOME" => home_dir()?,
+        "XDG_CONFIG_HOME" => xdg_config_home()?,
+        _ => {
+            return env::var(name)
+                .map_err(|_| format!("Environment variable ${name} is not set").into())
+        }
+    };
+    Ok(value.to_string_lossy().into_owned())
+}
+
+/// Expands a leading `~` and any `$VAR` / `${VAR}` references in a configured path.
+///
+/// # Arguments
+/// - `path`: The path as written in the configuration file, e.g. `~/.config/xfce4/panel/`.
+///
+/// # Returns
+/// - An `Ok(PathBuf)` containing the expanded path.
+/// - An `Err` if a referenced variable is not set or a `${` is not closed.
+pub fn expand_path(path: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let mut expanded = String::new();
+    let mut rest = path;
+
+    if rest == "~" || rest.starts_with("~/") {
+        expanded.push_str(&home_dir()?.to_string_lossy());
+        rest = &rest[1..];
+    }
+
+    while let Some(pos) = rest.find('$') {
+        expanded.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+
+        let (name, remainder) = if let Some(braced) = after.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| format!("Unclosed '${{' in path '{path}'"))?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+
+        if name.is_empty() {
+            expanded.push('$');
+        } else {
+            expanded.push_str(&lookup_var(name)?);
+        }
+        rest = remainder;
+    }
+    expanded.push_str(rest);
+
+    Ok(PathBuf::from(expanded))
+}
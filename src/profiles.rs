@@ -0,0 +1,281 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::paths::{xdg_config_home, APP_DIR_NAME};
+
+/// Profiles shipped with the tool, as `(file name, TOML source)`.
+///
+/// The generic profile matches every theme name, so it comes last.
+const BUILTIN_PROFILES: &[(&str, &str)] = &[
+    ("mint-y.toml", include_str!("../profiles/mint-y.toml")),
+    ("mint-l.toml", include_str!("../profiles/mint-l.toml")),
+    ("greybird.toml", include_str!("../profiles/greybird.toml")),
+    ("arc.toml", include_str!("../profiles/arc.toml")),
+    ("adwaita.toml", include_str!("../profiles/adwaita.toml")),
+    ("materia.toml", include_str!("../profiles/materia.toml")),
+    ("numix.toml", include_str!("../profiles/numix.toml")),
+    ("generic.toml", include_str!("../profiles/generic.toml")),
+];
+
+/// Profile used when no profile matches the theme name.
+const FALLBACK_PROFILE: &str = "generic";
+
+/// A CSS declaration updated in the theme: a rule selector and one of its properties.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProfileTarget {
+    /// Candidate selector lists, tried in order; the first one present in the theme is used.
+    pub selectors: Vec<String>,
+    /// Property that is set in the rule.
+    pub property: String,
+}
+
+/// The logical targets every profile maps onto theme rules.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProfileTargets {
+    /// Main color of the Whisker menu (`.background` in most themes).
+    pub menu_background: ProfileTarget,
+    /// Background of the Whisker menu item list.
+    pub menu_view: ProfileTarget,
+    /// Search bar when it is not in focus.
+    pub search_unfocused: ProfileTarget,
+    /// Search bar when it is in focus.
+    pub search_focused: ProfileTarget,
+    /// Border color of the Whisker menu.
+    pub border: ProfileTarget,
+}
+
+/// Where a profile was loaded from.
+#[derive(Debug, Clone, Default)]
+pub enum ProfileSource {
+    #[default]
+    Builtin,
+    File(PathBuf),
+}
+
+impl fmt::Display for ProfileSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileSource::Builtin => write!(f, "built-in"),
+            ProfileSource::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Describes how the selectors and properties of a family of themes are laid out.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ThemeProfile {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Theme name patterns (`*` wildcards, case-insensitive) the profile is selected for.
+    #[serde(default)]
+    pub themes: Vec<String>,
+    pub targets: ProfileTargets,
+    #[serde(skip)]
+    pub source: ProfileSource,
+}
+
+impl ThemeProfile {
+    /// Checks whether the profile applies to the given theme name.
+    pub fn matches_theme(&self, theme_name: &str) -> bool {
+        self.themes
+            .iter()
+            .any(|pattern| glob_match(&pattern.to_lowercase(), &theme_name.to_lowercase()))
+    }
+}
+
+/// Matches `text` against a pattern where `*` stands for any sequence of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Returns the directory holding user profiles
+/// (`$XDG_CONFIG_HOME/xfce4-transparent-whiskermenu/profiles`).
+pub fn user_profiles_dir() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(xdg_config_home()?.join(APP_DIR_NAME).join("profiles"))
+}
+
+fn parse_profile(content: &str, source: ProfileSource) -> Result<ThemeProfile, Box<dyn Error>> {
+    let mut profile: ThemeProfile =
+        toml::from_str(content).map_err(|e| format!("Invalid profile {source}: {e}"))?;
+    profile.source = source;
+    Ok(profile)
+}
+
+/// Loads every available profile.
+///
+/// User profiles come first, so they take precedence over a built-in profile with the
+/// same name or matching the same themes.
+///
+/// # Returns
+/// - An `Ok(Vec<ThemeProfile>)` with user profiles followed by the built-in ones.
+/// - An `Err` if a profile file cannot be read or parsed.
+pub fn load_profiles() -> Result<Vec<ThemeProfile>, Box<dyn Error>> {
+    let mut profiles = Vec::new();
+
+    let dir = user_profiles_dir()?;
+    if dir.is_dir() {
+        let mut files: Vec<PathBuf> = fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        files.sort();
+        for path in files {
+            let content = fs::read_to_string(&path)?;
+            profiles.push(parse_profile(&content, ProfileSource::File(path))?);
+        }
+    }
+
+    for (_, content) in BUILTIN_PROFILES {
+        let profile = parse_profile(content, ProfileSource::Builtin)?;
+        if !profiles.iter().any(|p| p.name == profile.name) {
+            profiles.push(profile);
+        }
+    }
+    Ok(profiles)
+}
+
+/// Derives the theme name from a theme CSS path such as
+/// `/usr/share/themes/Mint-L-Dark/gtk-3.0/gtk-dark.css`.
+pub fn theme_name_from_path(theme_path: &Path) -> Option<String> {
+    let gtk_dir = theme_path.parent()?;
+    gtk_dir
+        .file_name()?
+        .to_str()?
+        .starts_with("gtk-")
+        .then(|| gtk_dir.parent()?.file_name()?.to_str().map(str::to_string))
+        .flatten()
+}
+
+/// Chooses the profile for the configured theme.
+///
+/// A `profile` set in the configuration wins; otherwise the first profile whose `themes`
+/// patterns match the theme name is used, falling back to the generic profile.
+///
+/// # Returns
+/// - An `Ok(ThemeProfile)` with the chosen profile.
+/// - An `Err` if the configured profile does not exist or profiles cannot be loaded.
+pub fn select_profile(config: &Config) -> Result<ThemeProfile, Box<dyn Error>> {
+    let profiles = load_profiles()?;
+
+    if let Some(name) = &config.profile {
+        return profiles
+            .into_iter()
+            .find(|p| &p.name == name)
+            .ok_or_else(|| format!("Unknown theme profile '{name}' (see `profiles`)").into());
+    }
+
+    theme_name_from_path(&config.theme_path)
+        .and_then(|theme_name| profile_for_theme(&profiles, &theme_name))
+        .or_else(|| profiles.iter().find(|p| p.name == FALLBACK_PROFILE))
+        .cloned()
+        .ok_or_else(|| format!("Built-in profile '{FALLBACK_PROFILE}' is missing").into())
+}
+
+/// Returns the first profile whose `themes` patterns match `theme_name`.
+fn profile_for_theme<'a>(
+    profiles: &'a [ThemeProfile],
+    theme_name: &str,
+) -> Option<&'a ThemeProfile> {
+    profiles.iter().find(|p| p.matches_theme(theme_name))
+}
+
+/// Prints every available profile and marks the one selected for the configuration.
+///
+/// # Arguments
+/// - `config`: The loaded configuration, or `None` if no config file was found.
+pub fn print_profiles(config: Option<&Config>) -> Result<(), Box<dyn Error>> {
+    let selected = config.map(select_profile).transpose()?;
+    for profile in load_profiles()? {
+        let marker = if selected.as_ref().is_some_and(|s| s.name == profile.name) {
+            "*"
+        } else {
+            " "
+        };
+        println!(
+            "{marker} {:<10} {:<40} themes: {} ({})",
+            profile.name,
+            profile.description,
+            profile.themes.join(", "),
+            profile.source
+        );
+    }
+    println!(
+        "User profiles are read from {}",
+        user_profiles_dir()?.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtin_profiles() -> Vec<ThemeProfile> {
+        BUILTIN_PROFILES
+            .iter()
+            .map(|(_, content)| parse_profile(content, ProfileSource::Builtin).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn picks_the_profile_of_each_theme_family() {
+        let profiles = builtin_profiles();
+        for (theme, expected) in [
+            ("Mint-Y", "mint-y"),
+            ("Mint-Y-Dark-Aqua", "mint-y"),
+            ("Mint-L-Dark", "mint-l"),
+            ("Greybird-dark", "greybird"),
+            ("Arc", "arc"),
+            ("Arc-Darker", "arc"),
+            ("Adwaita-dark", "adwaita"),
+            ("Materia-dark-compact", "materia"),
+            ("Numix", "numix"),
+            ("Breeze", "generic"),
+            ("Archlabs", "generic"),
+        ] {
+            let profile = profile_for_theme(&profiles, theme).unwrap();
+            assert_eq!(profile.name, expected, "{theme}");
+        }
+    }
+
+    #[test]
+    fn reads_the_theme_name_from_the_stylesheet_path() {
+        assert_eq!(
+            theme_name_from_path(Path::new(
+                "/usr/share/themes/Mint-L-Dark/gtk-3.0/gtk-dark.css"
+            ))
+            .as_deref(),
+            Some("Mint-L-Dark")
+        );
+        assert_eq!(theme_name_from_path(Path::new("/tmp/gtk.css")), None);
+    }
+
+    #[test]
+    fn matches_theme_patterns() {
+        assert!(glob_match("mint-y*", "mint-y-dark"));
+        assert!(glob_match("*-dark", "greybird-dark"));
+        assert!(glob_match("a*b*c", "abbc"));
+        assert!(!glob_match("arc", "arc-dark"));
+        assert!(!glob_match("ab*ba", "aba"));
+    }
+}
@@ -0,0 +1,75 @@
+use std::env;
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+
+use crate::paths::{sudo_owner, sudo_user};
+
+/// Returns the display of the graphical session, if any.
+fn session_display() -> Option<(&'static str, String)> {
+    ["WAYLAND_DISPLAY", "DISPLAY"].into_iter().find_map(|name| {
+        env::var(name)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .map(|value| (name, value))
+    })
+}
+
+/// Returns whether `program` can be found in `PATH`.
+fn in_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .map(|path| env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+/// Restarts the running `xfce4-panel` so it rereads its settings and the Whisker Menu rc files.
+///
+/// Under `sudo` the panel is restarted as the invoking user with `runuser`, pointing it at
+/// that user's session bus, so it talks to the user's panel rather than root's. Nothing is
+/// done (apart from a message) when there is no X11 or Wayland session or `xfce4-panel` is
+/// not installed; the changes are already applied at that point.
+///
+/// # Errors
+/// Returns an error if `xfce4-panel -r` cannot be started or fails.
+pub fn reload_panel() -> Result<(), Box<dyn Error>> {
+    let Some((display_var, display)) = session_display() else {
+        println!("No X11 or Wayland session found (DISPLAY and WAYLAND_DISPLAY are unset); run `xfce4-panel -r` in your session to reload");
+        return Ok(());
+    };
+    if !in_path("xfce4-panel") {
+        println!("xfce4-panel is not installed; skipping the panel reload");
+        return Ok(());
+    }
+
+    let mut command = match (sudo_user(), sudo_owner()) {
+        (Some(user), Some((uid, _))) => {
+            let runtime_dir = format!("/run/user/{uid}");
+            let mut command = Command::new("runuser");
+            command.args(["-u", &user, "--", "xfce4-panel", "-r"]);
+            if Path::new(&runtime_dir).is_dir() {
+                command
+                    .env(
+                        "DBUS_SESSION_BUS_ADDRESS",
+                        format!("unix:path={runtime_dir}/bus"),
+                    )
+                    .env("XDG_RUNTIME_DIR", &runtime_dir);
+            }
+            command
+        }
+        _ => {
+            let mut command = Command::new("xfce4-panel");
+            command.arg("-r");
+            command
+        }
+    };
+    command.env(display_var, display);
+
+    let status = command
+        .status()
+        .map_err(|e| format!("Failed to run xfce4-panel -r: {e}"))?;
+    if !status.success() {
+        return Err(format!("xfce4-panel -r failed ({status})").into());
+    }
+    println!("Reloaded xfce4-panel");
+    Ok(())
+}
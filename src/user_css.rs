@@ -0,0 +1,66 @@
+/// Prefix of the comments delimiting the blocks this tool manages in the user `gtk.css`.
+const MARKER: &str = "xfce4-transparent-whiskermenu";
+
+/// Name of the Whisker Menu popup window, used to scope every managed rule.
+pub const WHISKER_WINDOW: &str = "#whiskermenu-window";
+
+fn begin_marker(id: &str) -> String {
+    format!("/* {MARKER}: begin {id} (managed block, edits are overwritten) */")
+}
+
+fn end_marker(id: &str) -> String {
+    format!("/* {MARKER}: end {id} */")
+}
+
+/// Replaces the managed block `id` in a user stylesheet, or appends it if it is missing.
+///
+/// Everything outside the block is left untouched.
+///
+/// # Arguments
+/// - `content`: The current contents of `~/.config/gtk-3.0/gtk.css`.
+/// - `id`: Identifier of the block (`menu`, `search` or `border`).
+/// - `body`: The CSS rules to place between the markers.
+pub fn set_managed_block(content: &str, id: &str, body: &str) -> String {
+    let begin = begin_marker(id);
+    let end = end_marker(id);
+    let block = format!("{begin}\n{}\n{end}", body.trim_end());
+
+    let begin_prefix = format!("/* {MARKER}: begin {id} ");
+    if let Some(start) = content.find(&begin_prefix) {
+        if let Some(end_offset) = content[start..].find(&end) {
+            let stop = start + end_offset + end.len();
+            return format!("{}{block}{}", &content[..start], &content[stop..]);
+        }
+    }
+
+    let mut updated = content.to_string();
+    if !updated.is_empty() {
+        if !updated.ends_with('\n') {
+            updated.push('\n');
+        }
+        updated.push('\n');
+    }
+    updated.push_str(&block);
+    updated.push('\n');
+    updated
+}
+
+/// Builds the `menu` block: the window background and the transparent item views.
+pub fn menu_block(background: &str, view_background: &str) -> String {
+    format!(
+        "{WHISKER_WINDOW},\n{WHISKER_WINDOW}.background {{\n  background-color: {background};\n}}\n\n\
+         {WHISKER_WINDOW} .view,\n{WHISKER_WINDOW} iconview,\n{WHISKER_WINDOW} treeview {{\n  background-color: {view_background};\n}}"
+    )
+}
+
+/// Builds the `search` block: the search entry, focused or not.
+pub fn search_block(background: &str) -> String {
+    format!(
+        "{WHISKER_WINDOW} entry,\n{WHISKER_WINDOW} entry:focus {{\n  background-color: {background};\n}}"
+    )
+}
+
+/// Builds the `border` block: the border of the search entry.
+pub fn border_block(border_color: &str) -> String {
+    format!("{WHISKER_WINDOW} entry {{\n  border-color: {border_color};\n}}")
+}
@@ -0,0 +1,402 @@
+use image::{DynamicImage, ImageReader};
+use std::cmp::Reverse;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::color::Color;
+use crate::config::update_config_keys;
+use crate::paths::xdg_config_home;
+use crate::xfconf::{Channel, XfconfValue};
+
+/// Longest side the wallpaper is scaled down to before its colors are clustered.
+const SAMPLE_SIZE: u32 = 96;
+
+/// Number of color clusters the wallpaper is reduced to.
+const CLUSTERS: usize = 6;
+
+/// Lloyd iterations run after seeding the clusters with median cut.
+const KMEANS_ITERATIONS: usize = 10;
+
+/// Clusters covering less of the image than this are not considered as accent.
+const MIN_ACCENT_SHARE: f64 = 0.02;
+
+/// Opacity written for the menu and panel background.
+const WALLPAPER_OPACITY: f64 = 0.8;
+
+/// Opacity written for the search bar.
+const WALLPAPER_SEARCH_OPACITY: f64 = 0.6;
+
+/// Colors extracted from a wallpaper.
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    /// Center of the largest color cluster.
+    pub dominant: Color,
+    /// The most colorful of the remaining clusters.
+    pub accent: Color,
+}
+
+/// A cluster of similar pixels.
+#[derive(Debug, Clone, Copy)]
+struct Cluster {
+    center: [f64; 3],
+    pixels: usize,
+}
+
+/// Returns the path of the xfconf `xfce4-desktop` channel file.
+pub fn desktop_channel_path() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(xdg_config_home()?
+        .join("xfce4")
+        .join("xfconf")
+        .join("xfce-perchannel-xml")
+        .join("xfce4-desktop.xml"))
+}
+
+/// Reads the wallpaper of the first workspace of every monitor from `xfce4-desktop.xml`.
+///
+/// # Returns
+/// - The `last-image` of each `/backdrop/screen0/monitor*/workspace0`, in file order.
+/// - An `Err` if the channel cannot be parsed.
+fn wallpaper_paths(desktop_xml: &str) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let channel = Channel::parse(desktop_xml)?;
+    let Some(screen) = channel.property("/backdrop/screen0") else {
+        return Ok(Vec::new());
+    };
+    let mut paths = Vec::new();
+    for monitor in &screen.properties {
+        if !monitor.name.starts_with("monitor") {
+            continue;
+        }
+        let image = monitor
+            .child("workspace0")
+            .and_then(|workspace| workspace.child("last-image"));
+        if let Some(image) = image {
+            if let XfconfValue::String(path) = image.value()? {
+                if !path.is_empty() {
+                    paths.push(PathBuf::from(path));
+                }
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// Finds the current wallpaper: the first configured backdrop image that exists.
+///
+/// # Errors
+/// Returns an error if `xfce4-desktop.xml` cannot be read or names no existing image.
+pub fn current_wallpaper() -> Result<PathBuf, Box<dyn Error>> {
+    let desktop = desktop_channel_path()?;
+    let content = std::fs::read_to_string(&desktop)
+        .map_err(|e| format!("Failed to read {}: {e}", desktop.display()))?;
+    let paths = wallpaper_paths(&content).map_err(|e| format!("{}: {e}", desktop.display()))?;
+    if let Some(path) = paths.iter().find(|path| path.is_file()) {
+        return Ok(path.clone());
+    }
+    match paths.first() {
+        Some(path) => Err(format!("The wallpaper {} does not exist", path.display()).into()),
+        None => Err(format!(
+            "No /backdrop/screen0/monitor*/workspace0/last-image in {}",
+            desktop.display()
+        )
+        .into()),
+    }
+}
+
+/// Decodes a PNG or JPEG image and returns its opaque pixels, scaled down.
+///
+/// # Errors
+/// Returns an error if the image cannot be read or decoded, or is fully transparent.
+fn sample_pixels(path: &Path) -> Result<Vec<[f64; 3]>, Box<dyn Error>> {
+    let image = ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()
+        .map_err(|e| format!("Failed to decode {}: {e}", path.display()))?;
+    let pixels = opaque_pixels(&image);
+    if pixels.is_empty() {
+        return Err(format!("{} has no opaque pixels", path.display()).into());
+    }
+    Ok(pixels)
+}
+
+/// Scales an image down to [`SAMPLE_SIZE`] and returns its opaque pixels as RGB in `0..=1`.
+fn opaque_pixels(image: &DynamicImage) -> Vec<[f64; 3]> {
+    image
+        .thumbnail(SAMPLE_SIZE, SAMPLE_SIZE)
+        .to_rgba8()
+        .pixels()
+        .filter(|pixel| pixel[3] >= 128)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]].map(|c| f64::from(c) / 255.0))
+        .collect()
+}
+
+/// Returns the mean color of a PNG or JPEG image.
+///
+/// # Errors
+/// Returns an error if the image cannot be read or decoded, or is fully transparent.
+pub fn average_color(path: &Path) -> Result<Color, Box<dyn Error>> {
+    Ok(to_color(mean(&sample_pixels(path)?)))
+}
+
+/// Decodes a PNG or JPEG image and extracts its palette.
+///
+/// The image is scaled down and its pixels are grouped with k-means, seeded by a median
+/// cut so the result is the same on every run. The largest cluster is the dominant color;
+/// the accent is the remaining cluster with the most chroma, weighted by its size.
+///
+/// # Errors
+/// Returns an error if the image cannot be read or decoded, or is fully transparent.
+pub fn extract_palette(path: &Path) -> Result<Palette, Box<dyn Error>> {
+    Ok(palette_of(&sample_pixels(path)?))
+}
+
+/// Clusters non-empty `pixels` and picks the dominant and accent colors.
+fn palette_of(pixels: &[[f64; 3]]) -> Palette {
+    let mut clusters = kmeans(pixels, median_cut(pixels, CLUSTERS));
+    clusters.sort_by_key(|cluster| Reverse(cluster.pixels));
+    let dominant = to_color(clusters[0].center);
+
+    let chroma = |center: [f64; 3]| {
+        center.iter().copied().fold(0.0, f64::max) - center.iter().copied().fold(1.0, f64::min)
+    };
+    let accent = clusters[1..]
+        .iter()
+        .filter(|cluster| cluster.pixels as f64 / pixels.len() as f64 >= MIN_ACCENT_SHARE)
+        .map(|cluster| {
+            let share = cluster.pixels as f64 / pixels.len() as f64;
+            (chroma(cluster.center) * share.sqrt(), cluster.center)
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map_or_else(|| dominant.lighten(0.1), |(_, center)| to_color(center));
+
+    Palette { dominant, accent }
+}
+
+fn to_color([red, green, blue]: [f64; 3]) -> Color {
+    Color {
+        red,
+        green,
+        blue,
+        alpha: 1.0,
+    }
+}
+
+fn mean(pixels: &[[f64; 3]]) -> [f64; 3] {
+    let mut sum = [0.0; 3];
+    for pixel in pixels {
+        for (total, channel) in sum.iter_mut().zip(pixel) {
+            *total += channel;
+        }
+    }
+    sum.map(|total| total / pixels.len() as f64)
+}
+
+/// Splits the pixels into up to `count` boxes, each time halving the box with the widest
+/// channel range at its median, and returns the mean of each box.
+fn median_cut(pixels: &[[f64; 3]], count: usize) -> Vec<[f64; 3]> {
+    let range = |pixels: &[[f64; 3]], channel: usize| {
+        let values = pixels.iter().map(|pixel| pixel[channel]);
+        values.clone().fold(0.0, f64::max) - values.fold(1.0, f64::min)
+    };
+    let widest = |pixels: &[[f64; 3]]| {
+        (0..3)
+            .map(|channel| (range(pixels, channel), channel))
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap_or((0.0, 0))
+    };
+
+    let mut boxes = vec![pixels.to_vec()];
+    while boxes.len() < count {
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, pixels)| pixels.len() > 1)
+            .map(|(index, pixels)| (index, widest(pixels)))
+            .filter(|(_, (range, _))| *range > 0.0)
+            .max_by(|a, b| a.1 .0.total_cmp(&b.1 .0))
+            .map(|(index, (_, channel))| (index, channel))
+        else {
+            break;
+        };
+        let mut pixels = boxes.swap_remove(index);
+        pixels.sort_by(|a, b| a[channel].total_cmp(&b[channel]));
+        let upper = pixels.split_off(pixels.len() / 2);
+        boxes.push(pixels);
+        boxes.push(upper);
+    }
+    boxes.iter().map(|pixels| mean(pixels)).collect()
+}
+
+/// Refines cluster centers with Lloyd's algorithm and counts the pixels of each cluster.
+fn kmeans(pixels: &[[f64; 3]], mut centers: Vec<[f64; 3]>) -> Vec<Cluster> {
+    let nearest = |centers: &[[f64; 3]], pixel: &[f64; 3]| {
+        let distance = |center: &[f64; 3]| {
+            center
+                .iter()
+                .zip(pixel)
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum::<f64>()
+        };
+        (0..centers.len())
+            .min_by(|&a, &b| distance(&centers[a]).total_cmp(&distance(&centers[b])))
+            .unwrap_or(0)
+    };
+
+    let mut members: Vec<Vec<[f64; 3]>> = Vec::new();
+    for _ in 0..KMEANS_ITERATIONS {
+        members = vec![Vec::new(); centers.len()];
+        for pixel in pixels {
+            members[nearest(&centers, pixel)].push(*pixel);
+        }
+        let updated: Vec<[f64; 3]> = members
+            .iter()
+            .zip(&centers)
+            .map(|(members, center)| {
+                if members.is_empty() {
+                    *center
+                } else {
+                    mean(members)
+                }
+            })
+            .collect();
+        if updated == centers {
+            break;
+        }
+        centers = updated;
+    }
+    centers
+        .into_iter()
+        .zip(&members)
+        .map(|(center, members)| Cluster {
+            center,
+            pixels: members.len(),
+        })
+        .filter(|cluster| cluster.pixels > 0)
+        .collect()
+}
+
+/// Sets `base_color` and `search_color` in the config file from the wallpaper.
+///
+/// The dominant color becomes `base_color` and the accent `search_color`, with
+/// `opacity` and `search_opacity` set to values that keep the menu readable.
+///
+/// # Arguments
+/// - `config_path`: The config file to update.
+/// - `image`: The image to use instead of the current wallpaper.
+/// - `dry_run`: Only print the changes to the config file.
+///
+/// # Errors
+/// Returns an error if no wallpaper is found, it cannot be decoded, or the config file
+/// cannot be read or written.
+pub fn from_wallpaper(
+    config_path: &Path,
+    image: Option<&Path>,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let image = match image {
+        Some(image) => image.to_path_buf(),
+        None => current_wallpaper()?,
+    };
+    let palette = extract_palette(&image)?;
+    println!("Wallpaper: {}", image.display());
+    println!("  dominant: {}", palette.dominant);
+    println!("  accent:   {}", palette.accent);
+
+    update_config_keys(
+        config_path,
+        &[
+            ("base_color", toml::Value::String(palette.dominant.to_css())),
+            ("opacity", toml::Value::Float(WALLPAPER_OPACITY)),
+            ("search_color", toml::Value::String(palette.accent.to_css())),
+            (
+                "search_opacity",
+                toml::Value::Float(WALLPAPER_SEARCH_OPACITY),
+            ),
+        ],
+        dry_run,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage, Rgba, RgbaImage};
+
+    const NAVY: [u8; 3] = [20, 30, 60];
+    const ORANGE: [u8; 3] = [230, 120, 20];
+    const GREY: [u8; 3] = [128, 128, 128];
+
+    /// Builds a 96×10 image of vertical stripes, each `(color, width)`.
+    fn stripes(stripes: &[([u8; 3], u32)]) -> Vec<[f64; 3]> {
+        let image = RgbImage::from_fn(SAMPLE_SIZE, 10, |x, _| {
+            let mut left = 0;
+            for (color, width) in stripes {
+                left += width;
+                if x < left {
+                    return Rgb(*color);
+                }
+            }
+            Rgb(stripes[stripes.len() - 1].0)
+        });
+        opaque_pixels(&DynamicImage::ImageRgb8(image))
+    }
+
+    fn css(center: [f64; 3]) -> String {
+        to_color(center).to_css()
+    }
+
+    fn hex([red, green, blue]: [u8; 3]) -> String {
+        format!("#{red:02x}{green:02x}{blue:02x}")
+    }
+
+    #[test]
+    fn median_cut_stops_at_uniform_boxes() {
+        let pixels = stripes(&[(NAVY, 48), (ORANGE, 48)]);
+        let mut centers: Vec<String> = median_cut(&pixels, CLUSTERS).into_iter().map(css).collect();
+        centers.sort();
+        assert_eq!(centers, [hex(NAVY), hex(ORANGE)]);
+    }
+
+    #[test]
+    fn kmeans_moves_seeds_onto_the_halves_and_drops_empty_clusters() {
+        let pixels = stripes(&[(NAVY, 64), (ORANGE, 32)]);
+        let clusters = kmeans(
+            &pixels,
+            vec![[0.0, 0.0, 0.5], [1.0, 1.0, 0.0], [0.0, 1.0, 1.0]],
+        );
+        let found: Vec<(String, usize)> = clusters
+            .iter()
+            .map(|cluster| (css(cluster.center), cluster.pixels))
+            .collect();
+        assert_eq!(found, [(hex(NAVY), 640), (hex(ORANGE), 320)]);
+    }
+
+    #[test]
+    fn averages_the_opaque_pixels() {
+        let pixels = stripes(&[([0, 0, 0], 48), ([255, 255, 255], 48)]);
+        assert_eq!(to_color(mean(&pixels)).to_rgb8(), [128, 128, 128]);
+
+        let image = RgbaImage::from_fn(SAMPLE_SIZE, 10, |x, _| {
+            if x < 48 {
+                Rgba([255, 0, 0, 0])
+            } else {
+                Rgba([0, 0, 255, 255])
+            }
+        });
+        let pixels = opaque_pixels(&DynamicImage::ImageRgba8(image));
+        assert_eq!(pixels.len(), 480);
+        assert_eq!(css(mean(&pixels)), "#0000ff");
+    }
+
+    #[test]
+    fn picks_the_largest_cluster_and_the_most_colorful_accent() {
+        let palette = palette_of(&stripes(&[(NAVY, 56), (GREY, 30), (ORANGE, 10)]));
+        assert_eq!(palette.dominant.to_css(), hex(NAVY));
+        assert_eq!(palette.accent.to_css(), hex(ORANGE));
+
+        let palette = palette_of(&stripes(&[(NAVY, 95), (ORANGE, 1)]));
+        assert_eq!(
+            palette.accent.to_css(),
+            palette.dominant.lighten(0.1).to_css()
+        );
+    }
+}
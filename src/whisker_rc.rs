@@ -0,0 +1,554 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+/// One line of a `whiskermenu-N.rc` file.
+#[derive(Debug, Clone)]
+enum RcLine {
+    /// A `key=value` line. `prefix` holds the raw text up to and including the `=`, so the
+    /// spacing of hand-edited lines survives a rewrite.
+    Entry {
+        key: String,
+        prefix: String,
+        value: String,
+    },
+    /// A comment, blank line, group header or anything else, kept verbatim.
+    Other(String),
+}
+
+/// A parsed Whisker Menu rc file that writes back everything it does not change.
+///
+/// Comments, the order of keys and keys unknown to this tool are preserved; only the
+/// values passed to [`RcFile::set`] are rewritten.
+#[derive(Debug, Clone)]
+pub struct RcFile {
+    lines: Vec<RcLine>,
+    trailing_newline: bool,
+}
+
+impl RcFile {
+    /// Parses the contents of an rc file. Every input is accepted; lines that are not
+    /// `key=value` pairs are kept as they are.
+    pub fn parse(content: &str) -> RcFile {
+        let lines = content
+            .lines()
+            .map(|line| {
+                let trimmed = line.trim_start();
+                if trimmed.starts_with('#') || trimmed.starts_with(';') || trimmed.starts_with('[')
+                {
+                    return RcLine::Other(line.to_string());
+                }
+                match line.split_once('=') {
+                    Some((key, value)) if !key.trim().is_empty() => {
+                        let spacing = &value[..value.len() - value.trim_start().len()];
+                        RcLine::Entry {
+                            key: key.trim().to_string(),
+                            prefix: format!("{key}={spacing}"),
+                            value: value.trim_start().to_string(),
+                        }
+                    }
+                    _ => RcLine::Other(line.to_string()),
+                }
+            })
+            .collect();
+        RcFile {
+            lines,
+            trailing_newline: content.is_empty() || content.ends_with('\n'),
+        }
+    }
+
+    /// Sets `key` to `value`.
+    ///
+    /// The first line assigning the key is updated in place. A missing key is appended to
+    /// the ungrouped keys at the top of the file, before the first `[group]` header.
+    ///
+    /// # Returns
+    /// `true` if the key was added or held a different value.
+    pub fn set(&mut self, key: &str, value: &str) -> bool {
+        let existing = self.lines.iter_mut().find_map(|line| match line {
+            RcLine::Entry {
+                key: entry_key,
+                value: entry_value,
+                ..
+            } if entry_key == key => Some(entry_value),
+            _ => None,
+        });
+        if let Some(entry_value) = existing {
+            if entry_value == value {
+                return false;
+            }
+            *entry_value = value.to_string();
+            return true;
+        }
+
+        let first_group = self
+            .lines
+            .iter()
+            .position(
+                |line| matches!(line, RcLine::Other(text) if text.trim_start().starts_with('[')),
+            )
+            .unwrap_or(self.lines.len());
+        let insert_at = self.lines[..first_group]
+            .iter()
+            .rposition(|line| matches!(line, RcLine::Entry { .. }))
+            .map_or(first_group, |index| index + 1);
+        self.lines.insert(
+            insert_at,
+            RcLine::Entry {
+                key: key.to_string(),
+                prefix: format!("{key}="),
+                value: value.to_string(),
+            },
+        );
+        true
+    }
+}
+
+impl fmt::Display for RcFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, line) in self.lines.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            match line {
+                RcLine::Entry { prefix, value, .. } => write!(f, "{prefix}{value}")?,
+                RcLine::Other(text) => write!(f, "{text}")?,
+            }
+        }
+        if self.trailing_newline && !self.lines.is_empty() {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// The appearance keys of a Whisker Menu instance.
+///
+/// Read from the `[whisker]` section of the config; only the keys that are set are written
+/// to the rc files. Icon sizes use Whisker Menu's scale from `-1` (no icon) to `6` (largest).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WhiskerMenuSettings {
+    /// `menu-opacity`, 0–100; defaults to the config's `opacity` when updating.
+    pub menu_opacity: Option<u32>,
+    /// `menu-width` in pixels.
+    pub menu_width: Option<u32>,
+    /// `menu-height` in pixels.
+    pub menu_height: Option<u32>,
+    /// `item-icon-size`.
+    pub item_icon_size: Option<i32>,
+    /// `category-icon-size`.
+    pub category_icon_size: Option<i32>,
+    /// `button-title`, the label of the panel button.
+    pub button_title: Option<String>,
+    /// `show-button-icon`.
+    pub show_button_icon: Option<bool>,
+    /// `show-button-title`.
+    pub show_button_title: Option<bool>,
+    /// `position-search-alternate`: search entry at the bottom.
+    pub position_search_alternate: Option<bool>,
+    /// `position-commands-alternate`: command buttons next to the search entry.
+    pub position_commands_alternate: Option<bool>,
+    /// `position-categories-alternate`: categories on the left.
+    pub position_categories_alternate: Option<bool>,
+    /// `position-categories-horizontal`: categories in a horizontal row.
+    pub position_categories_horizontal: Option<bool>,
+    /// `position-profile-alternate`: user profile at the bottom.
+    pub position_profile_alternate: Option<bool>,
+}
+
+impl WhiskerMenuSettings {
+    /// Checks the ranges of the numeric keys.
+    ///
+    /// # Errors
+    /// - `menu_opacity` is above 100.
+    /// - `menu_width` or `menu_height` is 0.
+    /// - An icon size is outside `-1..=6`.
+    pub fn validate(&self, section: &str) -> Result<(), Box<dyn Error>> {
+        if let Some(opacity) = self.menu_opacity.filter(|opacity| *opacity > 100) {
+            return Err(format!(
+                "Invalid {section}.menu_opacity {opacity}. Must be between 0 and 100."
+            )
+            .into());
+        }
+        for (key, size) in [
+            ("menu_width", self.menu_width),
+            ("menu_height", self.menu_height),
+        ] {
+            if size == Some(0) {
+                return Err(format!("Invalid {section}.{key} 0. Must be at least 1.").into());
+            }
+        }
+        for (key, size) in [
+            ("item_icon_size", self.item_icon_size),
+            ("category_icon_size", self.category_icon_size),
+        ] {
+            if let Some(size) = size.filter(|size| !(-1..=6).contains(size)) {
+                return Err(
+                    format!("Invalid {section}.{key} {size}. Must be between -1 and 6.").into(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns these settings with every key set in `overrides` replaced.
+    pub fn overlay(&self, overrides: &WhiskerMenuSettings) -> WhiskerMenuSettings {
+        WhiskerMenuSettings {
+            menu_opacity: overrides.menu_opacity.or(self.menu_opacity),
+            menu_width: overrides.menu_width.or(self.menu_width),
+            menu_height: overrides.menu_height.or(self.menu_height),
+            item_icon_size: overrides.item_icon_size.or(self.item_icon_size),
+            category_icon_size: overrides.category_icon_size.or(self.category_icon_size),
+            button_title: overrides
+                .button_title
+                .clone()
+                .or_else(|| self.button_title.clone()),
+            show_button_icon: overrides.show_button_icon.or(self.show_button_icon),
+            show_button_title: overrides.show_button_title.or(self.show_button_title),
+            position_search_alternate: overrides
+                .position_search_alternate
+                .or(self.position_search_alternate),
+            position_commands_alternate: overrides
+                .position_commands_alternate
+                .or(self.position_commands_alternate),
+            position_categories_alternate: overrides
+                .position_categories_alternate
+                .or(self.position_categories_alternate),
+            position_categories_horizontal: overrides
+                .position_categories_horizontal
+                .or(self.position_categories_horizontal),
+            position_profile_alternate: overrides
+                .position_profile_alternate
+                .or(self.position_profile_alternate),
+        }
+    }
+
+    /// Lists the keys that are set as rc `(key, value)` pairs, in a fixed order.
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        let numbers = [
+            ("menu-opacity", self.menu_opacity.map(i64::from)),
+            ("menu-width", self.menu_width.map(i64::from)),
+            ("menu-height", self.menu_height.map(i64::from)),
+            ("item-icon-size", self.item_icon_size.map(i64::from)),
+            ("category-icon-size", self.category_icon_size.map(i64::from)),
+        ];
+        let flags = [
+            ("show-button-icon", self.show_button_icon),
+            ("show-button-title", self.show_button_title),
+            ("position-search-alternate", self.position_search_alternate),
+            (
+                "position-commands-alternate",
+                self.position_commands_alternate,
+            ),
+            (
+                "position-categories-alternate",
+                self.position_categories_alternate,
+            ),
+            (
+                "position-categories-horizontal",
+                self.position_categories_horizontal,
+            ),
+            (
+                "position-profile-alternate",
+                self.position_profile_alternate,
+            ),
+        ];
+
+        let mut entries: Vec<(&'static str, String)> = numbers
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|value| (key, value.to_string())))
+            .collect();
+        if let Some(title) = &self.button_title {
+            entries.push(("button-title", title.clone()));
+        }
+        entries.extend(
+            flags
+                .into_iter()
+                .filter_map(|(key, value)| value.map(|value| (key, value.to_string()))),
+        );
+        entries
+    }
+
+    /// Writes every key that is set into an rc file, inserting missing keys.
+    ///
+    /// # Returns
+    /// Every key written, with `true` if its value changed.
+    pub fn apply_to(&self, rc: &mut RcFile) -> Vec<(&'static str, bool)> {
+        self.entries()
+            .into_iter()
+            .map(|(key, value)| (key, rc.set(key, &value)))
+            .collect()
+    }
+}
+
+/// Returns whether `id` names a Whisker Menu plugin instance, like `whiskermenu-5`.
+pub fn is_instance_id(id: &str) -> bool {
+    id.strip_prefix("whiskermenu-")
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// The `[whisker]` section of the config.
+///
+/// Plain keys apply to every instance; a `[whisker."whiskermenu-N"]` table overrides them
+/// for the plugin instance with that ID. An instance table also accepts `opacity` (0.0–1.0)
+/// as a shorthand for `menu_opacity`. Colors cannot be set per instance: they come from the
+/// GTK theme, which every instance shares.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(try_from = "toml::Table")]
+pub struct WhiskerConfig {
+    /// Settings for every instance.
+    pub defaults: WhiskerMenuSettings,
+    /// Overrides per plugin instance, keyed by `whiskermenu-N`.
+    pub instances: BTreeMap<String, WhiskerMenuSettings>,
+}
+
+impl TryFrom<toml::Table> for WhiskerConfig {
+    type Error = String;
+
+    fn try_from(mut table: toml::Table) -> Result<Self, Self::Error> {
+        let ids: Vec<String> = table
+            .iter()
+            .filter(|(_, value)| value.is_table())
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        let mut instances = BTreeMap::new();
+        for id in ids {
+            if !is_instance_id(&id) {
+                return Err(format!(
+                    "[whisker.\"{id}\"] is not a plugin instance; expected a name like \"whiskermenu-5\""
+                ));
+            }
+            let Some(toml::Value::Table(mut overrides)) = table.remove(&id) else {
+                continue;
+            };
+            if let Some(key) = ["base_color", "search_color", "search_opacity"]
+                .into_iter()
+                .find(|key| overrides.contains_key(*key))
+            {
+                return Err(format!(
+                    "[whisker.\"{id}\"] cannot set {key}: menu colors come from the GTK theme and are shared by every instance"
+                ));
+            }
+            if let Some(opacity) = overrides.remove("opacity") {
+                let opacity = opacity
+                    .as_float()
+                    .or_else(|| opacity.as_integer().map(|i| i as f64))
+                    .filter(|opacity| (0.0..=1.0).contains(opacity))
+                    .ok_or_else(|| {
+                        format!(
+                            "Invalid whisker.\"{id}\".opacity {opacity}. Must be between 0 and 1."
+                        )
+                    })?;
+                if overrides.contains_key("menu_opacity") {
+                    return Err(format!(
+                        "[whisker.\"{id}\"] sets both opacity and menu_opacity"
+                    ));
+                }
+                overrides.insert(
+                    "menu_opacity".to_string(),
+                    toml::Value::Integer((opacity * 100.0).round() as i64),
+                );
+            }
+            let settings = toml::Value::Table(overrides)
+                .try_into()
+                .map_err(|e| format!("[whisker.\"{id}\"]: {e}"))?;
+            instances.insert(id, settings);
+        }
+
+        let defaults = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e| format!("[whisker]: {e}"))?;
+        Ok(WhiskerConfig {
+            defaults,
+            instances,
+        })
+    }
+}
+
+impl WhiskerConfig {
+    /// Checks the defaults and every instance.
+    ///
+    /// # Errors
+    /// Returns an error if a key is out of range.
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        self.defaults.validate("whisker")?;
+        for (id, settings) in &self.instances {
+            settings.validate(&format!("whisker.\"{id}\""))?;
+        }
+        Ok(())
+    }
+
+    /// Lists the configured instances that have no rc file in `whisker_menu_dir`.
+    pub fn missing_instances(&self, whisker_menu_dir: &Path) -> Vec<&str> {
+        self.instances
+            .keys()
+            .filter(|id| !whisker_menu_dir.join(format!("{id}.rc")).is_file())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Resolves the settings of one instance.
+    ///
+    /// # Arguments
+    /// - `id`: The instance ID, e.g. `whiskermenu-5`.
+    /// - `menu_opacity`: `menu-opacity` derived from the config's `opacity`, used unless the
+    ///   section sets `menu_opacity`.
+    pub fn settings_for(&self, id: &str, menu_opacity: u32) -> WhiskerMenuSettings {
+        let mut settings = self.defaults.clone();
+        settings.menu_opacity.get_or_insert(menu_opacity);
+        match self.instances.get(id) {
+            Some(overrides) => settings.overlay(overrides),
+            None => settings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RC: &str = "\
+# written by Whisker Menu
+favorites=firefox.desktop,thunar.desktop
+menu-opacity = 100
+button-title=Applications
+custom-unknown-key=kept
+
+[action0]
+name=Log Out
+command=xfce4-session-logout
+";
+
+    #[test]
+    fn writes_back_an_unchanged_file_verbatim() {
+        assert_eq!(RcFile::parse(RC).to_string(), RC);
+        let without_newline = RC.trim_end();
+        assert_eq!(RcFile::parse(without_newline).to_string(), without_newline);
+        assert_eq!(RcFile::parse("").to_string(), "");
+    }
+
+    #[test]
+    fn updates_an_existing_key_in_place() {
+        let mut rc = RcFile::parse(RC);
+        assert!(rc.set("menu-opacity", "80"));
+        assert!(!rc.set("button-title", "Applications"));
+        assert_eq!(
+            rc.to_string(),
+            RC.replace("menu-opacity = 100", "menu-opacity = 80")
+        );
+    }
+
+    #[test]
+    fn inserts_a_missing_key_before_the_first_group() {
+        let mut rc = RcFile::parse(RC);
+        assert!(rc.set("menu-width", "450"));
+        assert_eq!(
+            rc.to_string(),
+            RC.replace("kept\n", "kept\nmenu-width=450\n")
+        );
+
+        let mut empty = RcFile::parse("");
+        assert!(empty.set("menu-width", "450"));
+        assert_eq!(empty.to_string(), "menu-width=450\n");
+    }
+
+    #[test]
+    fn keeps_unknown_keys_and_comments() {
+        let mut rc = RcFile::parse(RC);
+        let settings = WhiskerMenuSettings {
+            menu_opacity: Some(85),
+            show_button_icon: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.apply_to(&mut rc),
+            [("menu-opacity", true), ("show-button-icon", true)]
+        );
+        let written = rc.to_string();
+        for line in [
+            "# written by Whisker Menu",
+            "favorites=firefox.desktop,thunar.desktop",
+            "custom-unknown-key=kept",
+            "[action0]",
+            "command=xfce4-session-logout",
+            "menu-opacity = 85",
+            "show-button-icon=false",
+        ] {
+            assert!(written.lines().any(|l| l == line), "missing {line:?}");
+        }
+        assert!(written.find("show-button-icon").unwrap() < written.find("[action0]").unwrap());
+    }
+
+    fn whisker_config(toml: &str) -> Result<WhiskerConfig, String> {
+        WhiskerConfig::try_from(toml.parse::<toml::Table>().unwrap())
+    }
+
+    #[test]
+    fn resolves_instance_overrides_over_section_defaults() {
+        let config = whisker_config(
+            r#"
+            menu_width = 400
+            menu_opacity = 90
+            button_title = "Menu"
+
+            [whiskermenu-5]
+            menu_width = 600
+            show_button_icon = false
+            "#,
+        )
+        .unwrap();
+
+        let overridden = config.settings_for("whiskermenu-5", 75);
+        assert_eq!(overridden.menu_width, Some(600));
+        assert_eq!(overridden.show_button_icon, Some(false));
+        assert_eq!(overridden.menu_opacity, Some(90));
+        assert_eq!(overridden.button_title.as_deref(), Some("Menu"));
+
+        let other = config.settings_for("whiskermenu-1", 75);
+        assert_eq!(other.menu_width, Some(400));
+        assert_eq!(other.show_button_icon, None);
+        assert_eq!(other.menu_opacity, Some(90));
+    }
+
+    #[test]
+    fn falls_back_to_the_config_opacity() {
+        let config = whisker_config("menu_width = 400").unwrap();
+        assert_eq!(
+            config.settings_for("whiskermenu-1", 75).menu_opacity,
+            Some(75)
+        );
+
+        let config = whisker_config("[whiskermenu-5]\nopacity = 0.6").unwrap();
+        assert_eq!(
+            config.settings_for("whiskermenu-5", 75).menu_opacity,
+            Some(60)
+        );
+        assert_eq!(
+            config.settings_for("whiskermenu-1", 75).menu_opacity,
+            Some(75)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_instance_tables() {
+        for (toml, message) in [
+            ("[panel-3]\nmenu_width = 400", "not a plugin instance"),
+            (
+                "[whiskermenu-5]\nbase_color = \"#000\"",
+                "cannot set base_color",
+            ),
+            (
+                "[whiskermenu-5]\nopacity = 0.5\nmenu_opacity = 50",
+                "both opacity and menu_opacity",
+            ),
+            ("[whiskermenu-5]\nopacity = 1.5", "Must be between 0 and 1"),
+        ] {
+            let error = whisker_config(toml).unwrap_err();
+            assert!(error.contains(message), "{toml:?}: {error}");
+        }
+    }
+}
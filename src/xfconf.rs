@@ -0,0 +1,873 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+/// A typed xfconf property value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum XfconfValue {
+    /// A property that only groups child properties (`type="empty"`).
+    Empty,
+    String(String),
+    Bool(bool),
+    Int(i32),
+    Uint(u32),
+    Int64(i64),
+    Uint64(u64),
+    Double(f64),
+    /// A list of scalar values (`type="array"` with `<value>` children).
+    Array(Vec<XfconfValue>),
+    /// Any other type (`float`, `int16`, `uchar`, ...), kept as its raw text.
+    Other {
+        ty: String,
+        value: String,
+    },
+}
+
+impl XfconfValue {
+    /// Parses a scalar `type`/`value` attribute pair.
+    fn from_scalar(ty: &str, value: &str) -> Result<Self, Box<dyn Error>> {
+        let invalid = || format!("Invalid {ty} value '{value}'");
+        Ok(match ty {
+            "empty" => XfconfValue::Empty,
+            "string" => XfconfValue::String(value.to_string()),
+            "bool" => match value {
+                "true" => XfconfValue::Bool(true),
+                "false" => XfconfValue::Bool(false),
+                _ => return Err(invalid().into()),
+            },
+            "int" => XfconfValue::Int(value.parse().map_err(|_| invalid())?),
+            "uint" => XfconfValue::Uint(value.parse().map_err(|_| invalid())?),
+            "int64" => XfconfValue::Int64(value.parse().map_err(|_| invalid())?),
+            "uint64" => XfconfValue::Uint64(value.parse().map_err(|_| invalid())?),
+            "double" => XfconfValue::Double(value.parse().map_err(|_| invalid())?),
+            _ => XfconfValue::Other {
+                ty: ty.to_string(),
+                value: value.to_string(),
+            },
+        })
+    }
+
+    /// Returns the `type` attribute of the value.
+    pub fn type_name(&self) -> &str {
+        match self {
+            XfconfValue::Empty => "empty",
+            XfconfValue::String(_) => "string",
+            XfconfValue::Bool(_) => "bool",
+            XfconfValue::Int(_) => "int",
+            XfconfValue::Uint(_) => "uint",
+            XfconfValue::Int64(_) => "int64",
+            XfconfValue::Uint64(_) => "uint64",
+            XfconfValue::Double(_) => "double",
+            XfconfValue::Array(_) => "array",
+            XfconfValue::Other { ty, .. } => ty,
+        }
+    }
+
+    /// Returns the `value` attribute of a scalar, or `None` for empty properties and arrays.
+    fn scalar_text(&self) -> Option<String> {
+        match self {
+            XfconfValue::Empty | XfconfValue::Array(_) => None,
+            XfconfValue::String(value) => Some(value.clone()),
+            XfconfValue::Bool(value) => Some(value.to_string()),
+            XfconfValue::Int(value) => Some(value.to_string()),
+            XfconfValue::Uint(value) => Some(value.to_string()),
+            XfconfValue::Int64(value) => Some(value.to_string()),
+            XfconfValue::Uint64(value) => Some(value.to_string()),
+            XfconfValue::Double(value) => Some(value.to_string()),
+            XfconfValue::Other { value, .. } => Some(value.clone()),
+        }
+    }
+}
+
+impl fmt::Display for XfconfValue {
+    /// Formats the value for messages: strings quoted, arrays as `[a, b]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XfconfValue::Empty => f.write_str("(empty)"),
+            XfconfValue::String(value) => write!(f, "{value:?}"),
+            XfconfValue::Array(values) => {
+                f.write_str("[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_str("]")
+            }
+            scalar => f.write_str(&scalar.scalar_text().unwrap_or_default()),
+        }
+    }
+}
+
+/// One `<value>` element of an array property, stored as written.
+#[derive(Debug, Clone, PartialEq)]
+struct ArrayItem {
+    ty: String,
+    value: String,
+}
+
+/// A `<property>` element with its values and child properties.
+///
+/// A property read from a file remembers where it came from in the source. Only the parts
+/// of a property that are set (or that gain children) are regenerated when the channel is
+/// written; everything else is copied from the source.
+#[derive(Debug, Clone)]
+pub struct Property {
+    pub name: String,
+    ty: String,
+    value: Option<String>,
+    items: Vec<ArrayItem>,
+    pub properties: Vec<Property>,
+    /// Attributes other than `name`, `type` and `value` (e.g. `locked`), in file order.
+    extra: Vec<(String, String)>,
+    span: SourceSpan,
+}
+
+/// Where a property was found in the source of its channel.
+///
+/// Every range is `None` for a new property, and is cleared when the text it covers has to
+/// be regenerated.
+#[derive(Debug, Clone, Default)]
+struct SourceSpan {
+    /// The whole element; cleared when anything inside it changes.
+    element: Option<Range<usize>>,
+    /// The start tag; cleared when the value is set.
+    start_tag: Option<Range<usize>>,
+    /// The `<value>` elements of an array, with the whitespace and comments before them.
+    items: Option<Range<usize>>,
+    /// Whitespace and comments before the element.
+    leading: Option<Range<usize>>,
+    /// Whitespace and comments before the end tag.
+    trailing: Option<Range<usize>>,
+}
+
+impl Property {
+    /// Creates a property holding `value` and no children.
+    pub fn new(name: &str, value: &XfconfValue) -> Self {
+        let mut property = Property {
+            name: name.to_string(),
+            ty: String::new(),
+            value: None,
+            items: Vec::new(),
+            properties: Vec::new(),
+            extra: Vec::new(),
+            span: SourceSpan::default(),
+        };
+        property.set_value(value);
+        property
+    }
+
+    /// Returns the typed value of the property.
+    ///
+    /// # Errors
+    /// Returns an error if a value does not parse as its declared type.
+    pub fn value(&self) -> Result<XfconfValue, Box<dyn Error>> {
+        if self.ty == "array" {
+            return self
+                .items
+                .iter()
+                .map(|item| XfconfValue::from_scalar(&item.ty, &item.value))
+                .collect::<Result<_, _>>()
+                .map(XfconfValue::Array);
+        }
+        XfconfValue::from_scalar(&self.ty, self.value.as_deref().unwrap_or_default())
+    }
+
+    /// Replaces the value of the property, keeping its child properties.
+    ///
+    /// Setting the value the property already holds leaves its source text as it was.
+    pub fn set_value(&mut self, value: &XfconfValue) {
+        let ty = value.type_name().to_string();
+        let text = value.scalar_text();
+        let items: Vec<ArrayItem> = match value {
+            XfconfValue::Array(values) => values
+                .iter()
+                .map(|value| ArrayItem {
+                    ty: value.type_name().to_string(),
+                    value: value.scalar_text().unwrap_or_default(),
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        if ty == self.ty && text == self.value && items == self.items {
+            return;
+        }
+        self.ty = ty;
+        self.value = text;
+        self.items = items;
+        self.span.element = None;
+        self.span.start_tag = None;
+        self.span.items = None;
+    }
+
+    /// Returns the child property with the given name.
+    pub fn child(&self, name: &str) -> Option<&Property> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    /// Writes the element, copying every part that was not changed from `source`.
+    fn write(&self, source: &str, out: &mut String, depth: usize) {
+        if let Some(element) = &self.span.element {
+            out.push_str(&source[element.clone()]);
+            return;
+        }
+        let indent = "  ".repeat(depth);
+        let has_contents = !self.items.is_empty() || !self.properties.is_empty();
+        let start_tag = self
+            .span
+            .start_tag
+            .as_ref()
+            .map(|tag| &source[tag.clone()])
+            .filter(|tag| !(has_contents && tag.ends_with("/>")));
+        match start_tag {
+            Some(tag) => out.push_str(tag),
+            None => {
+                out.push_str(&format!(
+                    "<property name=\"{}\" type=\"{}\"",
+                    escape(&self.name),
+                    escape(&self.ty)
+                ));
+                if let Some(value) = &self.value {
+                    out.push_str(&format!(" value=\"{}\"", escape(value)));
+                }
+                for (key, value) in &self.extra {
+                    out.push_str(&format!(" {key}=\"{}\"", escape(value)));
+                }
+                out.push_str(if has_contents { ">" } else { "/>" });
+            }
+        }
+        if start_tag.is_some_and(|tag| tag.ends_with("/>"))
+            || (start_tag.is_none() && !has_contents)
+        {
+            return;
+        }
+
+        match (&self.span.start_tag, &self.span.items) {
+            (Some(_), Some(items)) => out.push_str(&source[items.clone()]),
+            _ => {
+                for item in &self.items {
+                    out.push_str(&format!(
+                        "\n{indent}  <value type=\"{}\" value=\"{}\"/>",
+                        escape(&item.ty),
+                        escape(&item.value)
+                    ));
+                }
+            }
+        }
+        for property in &self.properties {
+            match &property.span.leading {
+                Some(leading) => out.push_str(&source[leading.clone()]),
+                None => out.push_str(&format!("\n{indent}  ")),
+            }
+            property.write(source, out, depth + 1);
+        }
+        match &self.span.trailing {
+            Some(trailing) => out.push_str(&source[trailing.clone()]),
+            None => out.push_str(&format!("\n{indent}")),
+        }
+        out.push_str("</property>");
+    }
+}
+
+/// A parsed xfconf perchannel XML file, such as `xfce4-panel.xml`.
+///
+/// Properties are addressed by path like xfconf does, e.g.
+/// `/panels/panel-1/background-rgba`. Writing a channel copies every property that was not
+/// set from the source, byte for byte, together with comments and the escaping of its
+/// attributes. Properties that are set or added are written the way xfconfd writes them:
+/// two-space indentation, one element per line.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    source: String,
+    /// Attributes of the `<channel>` element (`name`, `version`), in file order.
+    attributes: Vec<(String, String)>,
+    pub properties: Vec<Property>,
+    /// The `<channel>` start tag.
+    start_tag: Range<usize>,
+    /// Whitespace and comments before `</channel>`, or `None` for `<channel/>`.
+    trailing: Option<Range<usize>>,
+}
+
+impl Channel {
+    /// Parses the contents of a perchannel XML file.
+    ///
+    /// # Errors
+    /// Returns an error naming the line of the first malformed element, unexpected text or
+    /// value that does not match its type.
+    pub fn parse(source: &str) -> Result<Channel, Box<dyn Error>> {
+        let mut reader = Reader { source, pos: 0 };
+        reader.skip_misc()?;
+        let tag_start = reader.pos;
+        let tag = reader.start_tag()?;
+        if tag.name != "channel" {
+            return Err(reader.error(&format!("expected <channel>, found <{}>", tag.name)));
+        }
+        let mut channel = Channel {
+            source: source.to_string(),
+            attributes: tag.attributes,
+            properties: Vec::new(),
+            start_tag: tag_start..reader.pos,
+            trailing: None,
+        };
+        if !tag.self_closing {
+            loop {
+                let before = reader.pos;
+                reader.skip_misc()?;
+                if reader.at_end_tag() {
+                    channel.trailing = Some(before..reader.pos);
+                    reader.end_tag("channel")?;
+                    break;
+                }
+                let element_start = reader.pos;
+                let tag = reader.start_tag()?;
+                if tag.name != "property" {
+                    return Err(reader.error(&format!("unexpected <{}> in <channel>", tag.name)));
+                }
+                let mut property = reader.property(tag, element_start)?;
+                property.span.leading = Some(before..element_start);
+                channel.properties.push(property);
+            }
+        }
+        reader.skip_misc()?;
+        if reader.pos < source.len() {
+            return Err(reader.error("unexpected content after </channel>"));
+        }
+        Ok(channel)
+    }
+
+    /// Returns the property at `path` (e.g. `/panels/panel-1/size`).
+    pub fn property(&self, path: &str) -> Option<&Property> {
+        let mut names = split_path(path);
+        let first = names.next()?;
+        let mut property = self.properties.iter().find(|p| p.name == first)?;
+        for name in names {
+            property = property.child(name)?;
+        }
+        Some(property)
+    }
+
+    /// Sets the property at `path`, creating it and any missing parent as needed.
+    ///
+    /// Missing parents are created as `empty` properties; new properties are appended after
+    /// their existing siblings.
+    ///
+    /// # Errors
+    /// Returns an error if `path` names no property.
+    pub fn set(&mut self, path: &str, value: &XfconfValue) -> Result<(), Box<dyn Error>> {
+        let names: Vec<&str> = split_path(path).collect();
+        let Some((last, parents)) = names.split_last() else {
+            return Err(format!("Invalid property path '{path}'").into());
+        };
+        if self
+            .property(path)
+            .is_some_and(|p| p.value().ok().as_ref() == Some(value))
+        {
+            return Ok(());
+        }
+        let mut siblings = &mut self.properties;
+        for name in parents {
+            let index = match siblings.iter().position(|p| p.name == *name) {
+                Some(index) => index,
+                None => {
+                    siblings.push(Property::new(name, &XfconfValue::Empty));
+                    siblings.len() - 1
+                }
+            };
+            let parent = &mut siblings[index];
+            parent.span.element = None;
+            siblings = &mut parent.properties;
+        }
+        match siblings.iter_mut().find(|p| p.name == *last) {
+            Some(property) => property.set_value(value),
+            None => siblings.push(Property::new(last, value)),
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = self.source.as_str();
+        let mut out = String::from(&source[..self.start_tag.start]);
+        let (end, trailing) = match &self.trailing {
+            Some(trailing) => {
+                out.push_str(&source[self.start_tag.clone()]);
+                (trailing.end, &source[trailing.clone()])
+            }
+            // `<channel/>`: a property has to be added to something to be written.
+            None if self.properties.is_empty() => return f.write_str(source),
+            None => {
+                out.push_str("<channel");
+                for (key, value) in &self.attributes {
+                    out.push_str(&format!(" {key}=\"{}\"", escape(value)));
+                }
+                out.push('>');
+                (self.start_tag.end, "\n")
+            }
+        };
+        for property in &self.properties {
+            match &property.span.leading {
+                Some(leading) => out.push_str(&source[leading.clone()]),
+                None => out.push_str("\n  "),
+            }
+            property.write(source, &mut out, 1);
+        }
+        out.push_str(trailing);
+        if self.trailing.is_none() {
+            out.push_str("</channel>");
+        }
+        out.push_str(&source[end..]);
+        f.write_str(&out)
+    }
+}
+
+/// Splits a property path into its names, ignoring empty segments.
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|name| !name.is_empty())
+}
+
+/// Escapes an attribute value the way xfconfd does (`g_markup_escape_text`).
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            '\u{1}'..='\u{8}'
+            | '\u{b}'
+            | '\u{c}'
+            | '\u{e}'..='\u{1f}'
+            | '\u{7f}'..='\u{84}'
+            | '\u{86}'..='\u{9f}' => escaped.push_str(&format!("&#x{:x};", u32::from(c))),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Resolves the entity and character references in an attribute value.
+fn unescape(value: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find(';')
+            .ok_or_else(|| format!("unterminated entity in '{value}'"))?;
+        let entity = &rest[start + 1..start + end];
+        let c = match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32)
+                .ok_or_else(|| format!("unknown entity '&{entity};'"))?,
+        };
+        unescaped.push(c);
+        rest = &rest[start + end + 1..];
+    }
+    unescaped.push_str(rest);
+    Ok(unescaped)
+}
+
+/// A start tag read by [`Reader::start_tag`].
+struct StartTag {
+    name: String,
+    attributes: Vec<(String, String)>,
+    self_closing: bool,
+}
+
+impl StartTag {
+    fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A minimal reader for the subset of XML used by xfconf.
+struct Reader<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn rest(&self) -> &str {
+        &self.source[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> Box<dyn Error> {
+        let line = self.source[..self.pos].matches('\n').count() + 1;
+        format!("line {line}: {message}").into()
+    }
+
+    /// Skips whitespace, comments, the XML declaration and a doctype.
+    fn skip_misc(&mut self) -> Result<(), Box<dyn Error>> {
+        loop {
+            self.pos = self.source.len() - self.rest().trim_start().len();
+            let trimmed = &self.source[self.pos..];
+            let terminator = if trimmed.starts_with("<?") {
+                "?>"
+            } else if trimmed.starts_with("<!--") {
+                "-->"
+            } else if trimmed.starts_with("<!") {
+                ">"
+            } else {
+                return Ok(());
+            };
+            let end = trimmed
+                .find(terminator)
+                .ok_or_else(|| self.error("unterminated declaration or comment"))?;
+            self.pos += end + terminator.len();
+        }
+    }
+
+    fn at_end_tag(&self) -> bool {
+        self.rest().starts_with("</")
+    }
+
+    fn start_tag(&mut self) -> Result<StartTag, Box<dyn Error>> {
+        if !self.rest().starts_with('<') {
+            return Err(self.error("expected an element"));
+        }
+        let end = self
+            .rest()
+            .find('>')
+            .ok_or_else(|| self.error("unterminated tag"))?;
+        let inner = &self.rest()[1..end];
+        let (inner, self_closing) = match inner.strip_suffix('/') {
+            Some(inner) => (inner, true),
+            None => (inner, false),
+        };
+        let name_end = inner
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(inner.len());
+        let name = inner[..name_end].to_string();
+        let attributes = self.attributes(&inner[name_end..])?;
+        self.pos += end + 1;
+        Ok(StartTag {
+            name,
+            attributes,
+            self_closing,
+        })
+    }
+
+    fn attributes(&self, mut text: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let mut attributes = Vec::new();
+        loop {
+            text = text.trim_start();
+            if text.is_empty() {
+                return Ok(attributes);
+            }
+            let eq = text
+                .find('=')
+                .ok_or_else(|| self.error("attribute without a value"))?;
+            let key = text[..eq].trim().to_string();
+            let after = text[eq + 1..].trim_start();
+            let quote = after
+                .chars()
+                .next()
+                .filter(|c| *c == '"' || *c == '\'')
+                .ok_or_else(|| self.error(&format!("unquoted value for attribute '{key}'")))?;
+            let close = after[1..]
+                .find(quote)
+                .ok_or_else(|| self.error(&format!("unterminated value for attribute '{key}'")))?;
+            let value = unescape(&after[1..1 + close]).map_err(|e| self.error(&e))?;
+            attributes.push((key, value));
+            text = &after[close + 2..];
+        }
+    }
+
+    fn end_tag(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        let expected = format!("</{name}");
+        if !self.rest().starts_with(&expected) {
+            return Err(self.error(&format!("expected </{name}>")));
+        }
+        let end = self
+            .rest()
+            .find('>')
+            .ok_or_else(|| self.error("unterminated tag"))?;
+        if !self.rest()[expected.len()..end].trim().is_empty() {
+            return Err(self.error(&format!("expected </{name}>")));
+        }
+        self.pos += end + 1;
+        Ok(())
+    }
+
+    /// Reads the contents of a `<property>` whose start tag, beginning at `start`, was just
+    /// read.
+    fn property(&mut self, tag: StartTag, start: usize) -> Result<Property, Box<dyn Error>> {
+        let name = tag
+            .attribute("name")
+            .ok_or_else(|| self.error("<property> without a name"))?
+            .to_string();
+        let ty = tag
+            .attribute("type")
+            .ok_or_else(|| self.error(&format!("property '{name}' has no type")))?
+            .to_string();
+        let value = tag.attribute("value").map(str::to_string);
+        if ty != "array" {
+            XfconfValue::from_scalar(&ty, value.as_deref().unwrap_or_default())
+                .map_err(|e| self.error(&format!("property '{name}': {e}")))?;
+        }
+        let extra = tag
+            .attributes
+            .iter()
+            .filter(|(key, _)| !matches!(key.as_str(), "name" | "type" | "value"))
+            .cloned()
+            .collect();
+        let mut property = Property {
+            name,
+            ty,
+            value,
+            items: Vec::new(),
+            properties: Vec::new(),
+            extra,
+            span: SourceSpan {
+                start_tag: Some(start..self.pos),
+                ..SourceSpan::default()
+            },
+        };
+        if tag.self_closing {
+            property.span.element = Some(start..self.pos);
+            return Ok(property);
+        }
+
+        let contents_start = self.pos;
+        loop {
+            let before = self.pos;
+            self.skip_misc()?;
+            if self.at_end_tag() {
+                property.span.trailing = Some(before..self.pos);
+                self.end_tag("property")?;
+                property.span.element = Some(start..self.pos);
+                return Ok(property);
+            }
+            let element_start = self.pos;
+            let child = self.start_tag()?;
+            match child.name.as_str() {
+                "property" => {
+                    let mut child = self.property(child, element_start)?;
+                    child.span.leading = Some(before..element_start);
+                    property.properties.push(child);
+                }
+                "value" if !property.properties.is_empty() => {
+                    return Err(self.error(&format!(
+                        "<value> after a child property in property '{}'",
+                        property.name
+                    )))
+                }
+                "value" => {
+                    let ty = child.attribute("type").unwrap_or("string").to_string();
+                    let value = child.attribute("value").unwrap_or_default().to_string();
+                    XfconfValue::from_scalar(&ty, &value)
+                        .map_err(|e| self.error(&format!("property '{}': {e}", property.name)))?;
+                    property.items.push(ArrayItem { ty, value });
+                    if !child.self_closing {
+                        self.skip_misc()?;
+                        self.end_tag("value")?;
+                    }
+                    property.span.items = Some(contents_start..self.pos);
+                }
+                other => {
+                    return Err(self.error(&format!(
+                        "unexpected <{other}> in property '{}'",
+                        property.name
+                    )))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PANEL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+
+<channel name="xfce4-panel" version="1.0">
+  <!-- edited by hand -->
+  <property name="configver" type="int" value="2"/>
+  <property name="panels" type="array">
+    <value type="int" value="1"/>
+    <property name="panel-1" type="empty">
+      <property name="position" type="string" value="p=8;x=0;y=0"/>
+      <property name="size" type="uint" value="30"/>
+      <property name="background-rgba" type="array">
+        <value type="double" value="0.000000"/>
+        <value type="double" value="0.5"/>
+        <value type="double" value="1"/>
+        <value type="double" value="1"/>
+      </property>
+    </property>
+  </property>
+  <property name="plugins" type="empty">
+    <property name="plugin-1" type="string" value="whiskermenu">
+      <property name="button-title" type="string" value="Rock &#39;n&#39; roll &amp; more"/>
+    </property>
+  </property>
+</channel>
+"#;
+
+    fn rgba(values: [f64; 4]) -> XfconfValue {
+        XfconfValue::Array(values.map(XfconfValue::Double).to_vec())
+    }
+
+    #[test]
+    fn round_trips_byte_for_byte() {
+        let channel = Channel::parse(PANEL).unwrap();
+        assert_eq!(channel.to_string(), PANEL);
+    }
+
+    #[test]
+    fn reads_typed_values() {
+        let channel = Channel::parse(PANEL).unwrap();
+        let value = |path: &str| channel.property(path).unwrap().value().unwrap();
+        assert_eq!(value("/configver"), XfconfValue::Int(2));
+        assert_eq!(value("/panels/panel-1/size"), XfconfValue::Uint(30));
+        assert_eq!(
+            value("/panels"),
+            XfconfValue::Array(vec![XfconfValue::Int(1)])
+        );
+        assert_eq!(
+            value("/panels/panel-1/background-rgba"),
+            rgba([0.0, 0.5, 1.0, 1.0])
+        );
+        assert_eq!(
+            value("/plugins/plugin-1/button-title"),
+            XfconfValue::String("Rock 'n' roll & more".to_string())
+        );
+        assert!(channel.property("/panels/panel-2").is_none());
+    }
+
+    #[test]
+    fn setting_a_value_rewrites_only_that_property() {
+        let mut channel = Channel::parse(PANEL).unwrap();
+        channel
+            .set(
+                "/panels/panel-1/background-rgba",
+                &rgba([0.25, 0.5, 1.0, 0.8]),
+            )
+            .unwrap();
+        channel
+            .set("/panels/panel-1/size", &XfconfValue::Uint(32))
+            .unwrap();
+        let expected = PANEL
+            .replace(
+                r#"        <value type="double" value="0.000000"/>
+        <value type="double" value="0.5"/>
+        <value type="double" value="1"/>
+        <value type="double" value="1"/>"#,
+                r#"        <value type="double" value="0.25"/>
+        <value type="double" value="0.5"/>
+        <value type="double" value="1"/>
+        <value type="double" value="0.8"/>"#,
+            )
+            .replace(r#"value="30""#, r#"value="32""#);
+        assert_eq!(channel.to_string(), expected);
+    }
+
+    #[test]
+    fn setting_the_same_value_keeps_the_source() {
+        let mut channel = Channel::parse(PANEL).unwrap();
+        channel
+            .set(
+                "/panels/panel-1/background-rgba",
+                &rgba([0.0, 0.5, 1.0, 1.0]),
+            )
+            .unwrap();
+        channel
+            .set(
+                "/plugins/plugin-1/button-title",
+                &XfconfValue::String("Rock 'n' roll & more".to_string()),
+            )
+            .unwrap();
+        assert_eq!(channel.to_string(), PANEL);
+    }
+
+    #[test]
+    fn adds_missing_properties_and_parents() {
+        let mut channel = Channel::parse(PANEL).unwrap();
+        channel
+            .set("/panels/panel-1/background-style", &XfconfValue::Uint(1))
+            .unwrap();
+        channel
+            .set("/panels/panel-2/size", &XfconfValue::Uint(24))
+            .unwrap();
+        channel
+            .set(
+                "/plugins/plugin-2",
+                &XfconfValue::String("clock".to_string()),
+            )
+            .unwrap();
+        let expected = PANEL
+            .replace(
+                "      </property>\n    </property>\n  </property>\n  <property name=\"plugins\"",
+                "      </property>\n      <property name=\"background-style\" type=\"uint\" value=\"1\"/>\n    </property>\n    <property name=\"panel-2\" type=\"empty\">\n      <property name=\"size\" type=\"uint\" value=\"24\"/>\n    </property>\n  </property>\n  <property name=\"plugins\"",
+            )
+            .replace(
+                "    </property>\n  </property>\n</channel>",
+                "    </property>\n    <property name=\"plugin-2\" type=\"string\" value=\"clock\"/>\n  </property>\n</channel>",
+            );
+        assert_eq!(channel.to_string(), expected);
+    }
+
+    #[test]
+    fn adds_children_to_a_self_closing_property() {
+        let source = "<channel name=\"c\" version=\"1.0\">\n  <property name=\"a\" type=\"empty\"/>\n</channel>\n";
+        let mut channel = Channel::parse(source).unwrap();
+        channel.set("/a/b", &XfconfValue::Bool(true)).unwrap();
+        assert_eq!(
+            channel.to_string(),
+            "<channel name=\"c\" version=\"1.0\">\n  <property name=\"a\" type=\"empty\">\n    <property name=\"b\" type=\"bool\" value=\"true\"/>\n  </property>\n</channel>\n"
+        );
+
+        let mut empty = Channel::parse("<channel name=\"c\" version=\"1.0\"/>\n").unwrap();
+        empty.set("/a", &XfconfValue::Int(-1)).unwrap();
+        assert_eq!(
+            empty.to_string(),
+            "<channel name=\"c\" version=\"1.0\">\n  <property name=\"a\" type=\"int\" value=\"-1\"/>\n</channel>\n"
+        );
+    }
+
+    #[test]
+    fn escapes_like_glib() {
+        assert_eq!(
+            escape("a'b\"c<d>&\u{1}"),
+            "a&#39;b&quot;c&lt;d&gt;&amp;&#x1;"
+        );
+        assert_eq!(
+            unescape("a&#39;b&apos;&quot;&#x41;&lt;").unwrap(),
+            "a'b'\"A<"
+        );
+        assert!(unescape("&bogus;").is_err());
+    }
+
+    #[test]
+    fn reports_malformed_input() {
+        let error = |source: &str| Channel::parse(source).unwrap_err().to_string();
+        assert_eq!(
+            error("<channel>\n  <property name=\"a\" type=\"int\" value=\"x\"/>\n</channel>"),
+            "line 2: property 'a': Invalid int value 'x'"
+        );
+        assert!(
+            error("<channel>\n  <property type=\"int\"/>\n</channel>").contains("without a name")
+        );
+        assert!(error("<channel>\n</channel>\ntrailing").contains("after </channel>"));
+        assert!(error("<property/>").contains("expected <channel>"));
+    }
+}
@@ -0,0 +1,704 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use zbus::blocking::fdo::DBusProxy;
+use zbus::blocking::{connection, Connection};
+use zbus::names::BusName;
+use zbus::zvariant::{OwnedValue, Value};
+
+use crate::config::XfconfMode;
+use crate::paths::sudo_owner;
+use crate::xfconf::XfconfValue;
+
+/// Well-known name, object path and interface of the xfconf daemon.
+const XFCONF_SERVICE: &str = "org.xfce.Xfconf";
+const XFCONF_PATH: &str = "/org/xfce/Xfconf";
+const XFCONF_INTERFACE: &str = "org.xfce.Xfconf";
+
+/// Error xfconfd returns from `GetProperty` for a property that is not set.
+const PROPERTY_NOT_FOUND: &str = "org.xfce.Xfconf.Error.PropertyNotFound";
+
+/// A property set through xfconfd, with the value it replaced.
+///
+/// Snapshots record these so `restore` can put the old values back through xfconfd.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PropertyChange {
+    pub channel: String,
+    pub property: String,
+    /// The value before the update, or `None` if the property was not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<XfconfValue>,
+    /// The value set by the update.
+    pub value: XfconfValue,
+}
+
+impl fmt::Display for PropertyChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}: ", self.channel, self.property)?;
+        match &self.previous {
+            Some(previous) => write!(f, "{previous}")?,
+            None => f.write_str("(unset)")?,
+        }
+        write!(f, " -> {}", self.value)
+    }
+}
+
+/// A session bus connection to xfconfd.
+///
+/// Setting properties through xfconfd applies them to the running panel immediately and
+/// lets xfconfd persist them, instead of racing it by editing the channel file.
+pub struct XfconfBus {
+    connection: Connection,
+}
+
+impl XfconfBus {
+    /// Connects to the session bus and checks that xfconfd runs or can be activated there.
+    ///
+    /// Under `sudo` the bus of the invoking user at `/run/user/<uid>/bus` is used.
+    ///
+    /// # Errors
+    /// Returns an error if there is no session bus or it does not provide `org.xfce.Xfconf`.
+    pub fn connect() -> Result<XfconfBus, Box<dyn Error>> {
+        let connection = match sudo_owner() {
+            Some((uid, _)) => {
+                let address = format!("unix:path=/run/user/{uid}/bus");
+                connection::Builder::address(address.as_str())?.build()
+            }
+            None => Connection::session(),
+        }
+        .map_err(|e| format!("no session bus: {e}"))?;
+        XfconfBus::with_connection(connection)
+    }
+
+    /// Uses an open bus connection after checking that it provides `org.xfce.Xfconf`.
+    fn with_connection(connection: Connection) -> Result<XfconfBus, Box<dyn Error>> {
+        let dbus = DBusProxy::new(&connection)?;
+        let name = BusName::try_from(XFCONF_SERVICE)?;
+        let running = dbus.name_has_owner(name.clone())?;
+        let activatable = dbus
+            .list_activatable_names()?
+            .iter()
+            .any(|activatable| activatable.as_str() == XFCONF_SERVICE);
+        if !running && !activatable {
+            return Err(format!("{XFCONF_SERVICE} is not available on the session bus").into());
+        }
+        Ok(XfconfBus { connection })
+    }
+
+    fn call<B>(&self, method: &str, body: &B) -> zbus::Result<zbus::message::Message>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        self.connection.call_method(
+            Some(XFCONF_SERVICE),
+            XFCONF_PATH,
+            Some(XFCONF_INTERFACE),
+            method,
+            body,
+        )
+    }
+
+    /// Reads one property with `GetProperty`.
+    ///
+    /// # Returns
+    /// - `Ok(Some(value))` with the value xfconfd holds.
+    /// - `Ok(None)` if the property is not set.
+    /// - An `Err` if the call fails or the value has a type xfconf files cannot hold.
+    pub fn get_property(
+        &self,
+        channel: &str,
+        path: &str,
+    ) -> Result<Option<XfconfValue>, Box<dyn Error>> {
+        let reply = match self.call("GetProperty", &(channel, path)) {
+            Ok(reply) => reply,
+            Err(zbus::Error::MethodError(name, _, _)) if name.as_str() == PROPERTY_NOT_FOUND => {
+                return Ok(None)
+            }
+            Err(e) => return Err(format!("GetProperty {channel}{path} failed: {e}").into()),
+        };
+        let value: OwnedValue = reply.body().deserialize()?;
+        from_variant(&value)
+            .map(Some)
+            .map_err(|e| format!("{channel}{path}: {e}").into())
+    }
+
+    /// Sets one property with `SetProperty`.
+    ///
+    /// # Errors
+    /// Returns an error if the value cannot be sent or xfconfd rejects it.
+    pub fn set_property(
+        &self,
+        channel: &str,
+        path: &str,
+        value: &XfconfValue,
+    ) -> Result<(), Box<dyn Error>> {
+        self.call("SetProperty", &(channel, path, to_variant(value)?))
+            .map_err(|e| format!("SetProperty {channel}{path} failed: {e}"))?;
+        Ok(())
+    }
+
+    /// Removes one property with `ResetProperty`.
+    ///
+    /// # Errors
+    /// Returns an error if xfconfd rejects the call.
+    pub fn reset_property(&self, channel: &str, path: &str) -> Result<(), Box<dyn Error>> {
+        self.call("ResetProperty", &(channel, path, false))
+            .map_err(|e| format!("ResetProperty {channel}{path} failed: {e}"))?;
+        Ok(())
+    }
+
+    /// Compares the wanted values of a channel with the values xfconfd holds now.
+    ///
+    /// # Returns
+    /// - The properties whose live value differs, with the value they hold now.
+    /// - An `Err` if a property cannot be read.
+    pub fn pending_changes(
+        &self,
+        channel: &str,
+        wanted: &[(String, XfconfValue)],
+    ) -> Result<Vec<PropertyChange>, Box<dyn Error>> {
+        let mut changes = Vec::new();
+        for (path, value) in wanted {
+            let previous = self.get_property(channel, path)?;
+            if previous.as_ref() != Some(value) {
+                changes.push(PropertyChange {
+                    channel: channel.to_string(),
+                    property: path.clone(),
+                    previous,
+                    value: value.clone(),
+                });
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Sets every changed property as a single all-or-nothing step.
+    ///
+    /// If a property cannot be set, the ones already set get their previous values back.
+    ///
+    /// # Returns
+    /// - `Ok(())` once every property is set.
+    /// - An `Err` naming the property that failed and any property that could not be
+    ///   restored.
+    pub fn apply(&self, changes: &[PropertyChange]) -> Result<(), Box<dyn Error>> {
+        for (index, change) in changes.iter().enumerate() {
+            if let Err(e) = self.set_property(&change.channel, &change.property, &change.value) {
+                let failed = self.revert(&changes[..index]);
+                let mut message = e.to_string();
+                if failed.is_empty() {
+                    message.push_str("; all properties were rolled back");
+                } else {
+                    message.push_str(&format!("; rollback failed for: {}", failed.join(", ")));
+                }
+                return Err(message.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Gives changed properties their previous values, removing the ones that were not set.
+    ///
+    /// # Returns
+    /// The `channel/property` names that could not be restored.
+    pub fn revert(&self, changes: &[PropertyChange]) -> Vec<String> {
+        changes
+            .iter()
+            .rev()
+            .filter(|change| self.restore(change).is_err())
+            .map(|change| format!("{}{}", change.channel, change.property))
+            .collect()
+    }
+
+    /// Puts back the value a property held before `change`.
+    pub fn restore(&self, change: &PropertyChange) -> Result<(), Box<dyn Error>> {
+        match &change.previous {
+            Some(previous) => self.set_property(&change.channel, &change.property, previous),
+            None => self.reset_property(&change.channel, &change.property),
+        }
+    }
+}
+
+/// Connects to xfconfd following the `xfconf` setting.
+///
+/// # Arguments
+/// - `mode`: The `xfconf` setting of the configuration.
+/// - `fallback`: The file written instead when xfconfd is not used.
+///
+/// # Returns
+/// - `Ok(Some(XfconfBus))` if the properties should be set over D-Bus.
+/// - `Ok(None)` if `fallback` should be written instead.
+/// - An `Err` if `xfconf = "dbus"` and xfconfd is not available.
+pub fn connect_xfconf(
+    mode: XfconfMode,
+    fallback: &Path,
+) -> Result<Option<XfconfBus>, Box<dyn Error>> {
+    match mode {
+        XfconfMode::File => Ok(None),
+        XfconfMode::Dbus => XfconfBus::connect()
+            .map(Some)
+            .map_err(|e| format!("xfconf over D-Bus: {e}").into()),
+        XfconfMode::Auto => match XfconfBus::connect() {
+            Ok(bus) => Ok(Some(bus)),
+            Err(e) => {
+                println!(
+                    "xfconf over D-Bus is not available ({e}); writing {}",
+                    fallback.display()
+                );
+                Ok(None)
+            }
+        },
+    }
+}
+
+/// Converts a value to the variant xfconfd expects; arrays are sent as `av`.
+fn to_variant(value: &XfconfValue) -> Result<Value<'static>, Box<dyn Error>> {
+    Ok(match value {
+        XfconfValue::String(value) => Value::from(value.clone()),
+        XfconfValue::Bool(value) => Value::from(*value),
+        XfconfValue::Int(value) => Value::from(*value),
+        XfconfValue::Uint(value) => Value::from(*value),
+        XfconfValue::Int64(value) => Value::from(*value),
+        XfconfValue::Uint64(value) => Value::from(*value),
+        XfconfValue::Double(value) => Value::from(*value),
+        XfconfValue::Array(values) => Value::from(
+            values
+                .iter()
+                .map(to_variant)
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        XfconfValue::Empty | XfconfValue::Other { .. } => {
+            return Err(format!("cannot send a {} value over D-Bus", value.type_name()).into())
+        }
+    })
+}
+
+/// Converts a variant returned by xfconfd; `av` arrays become arrays of their items.
+fn from_variant(value: &Value) -> Result<XfconfValue, Box<dyn Error>> {
+    Ok(match value {
+        Value::Str(value) => XfconfValue::String(value.to_string()),
+        Value::Bool(value) => XfconfValue::Bool(*value),
+        Value::I32(value) => XfconfValue::Int(*value),
+        Value::U32(value) => XfconfValue::Uint(*value),
+        Value::I64(value) => XfconfValue::Int64(*value),
+        Value::U64(value) => XfconfValue::Uint64(*value),
+        Value::F64(value) => XfconfValue::Double(*value),
+        Value::U8(value) => other("uchar", value),
+        Value::I16(value) => other("int16", value),
+        Value::U16(value) => other("uint16", value),
+        Value::Value(value) => from_variant(value)?,
+        Value::Array(values) => XfconfValue::Array(
+            values
+                .iter()
+                .map(from_variant)
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        value => {
+            return Err(format!("unsupported value of type {}", value.value_signature()).into())
+        }
+    })
+}
+
+fn other(ty: &str, value: impl ToString) -> XfconfValue {
+    XfconfValue::Other {
+        ty: ty.to_string(),
+        value: value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::io::{BufRead, BufReader};
+    use std::path::PathBuf;
+    use std::process::{Child, Command, Stdio};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    type Store = Arc<Mutex<BTreeMap<String, OwnedValue>>>;
+
+    const BUS_CONFIG: &str = r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-BUS Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:tmpdir=/tmp</listen>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#;
+
+    #[derive(Debug, zbus::DBusError)]
+    #[zbus(prefix = "org.xfce.Xfconf.Error")]
+    enum StubError {
+        #[zbus(error)]
+        ZBus(zbus::Error),
+        PropertyNotFound(String),
+        PermissionDenied(String),
+    }
+
+    /// Just enough of xfconfd for [`XfconfBus`]: one store for all channels.
+    struct StubXfconf {
+        store: Store,
+        /// A property `SetProperty` refuses, to make an update fail halfway.
+        read_only: Option<String>,
+    }
+
+    #[zbus::interface(name = "org.xfce.Xfconf")]
+    impl StubXfconf {
+        fn get_property(&self, channel: &str, property: &str) -> Result<OwnedValue, StubError> {
+            let key = format!("{channel}{property}");
+            let store = self.store.lock().unwrap();
+            match store.get(&key) {
+                Some(value) => Ok(value.try_clone().map_err(zbus::Error::from)?),
+                None => Err(StubError::PropertyNotFound(key)),
+            }
+        }
+
+        fn set_property(
+            &self,
+            channel: &str,
+            property: &str,
+            value: OwnedValue,
+        ) -> Result<(), StubError> {
+            let key = format!("{channel}{property}");
+            if self.read_only.as_deref() == Some(key.as_str()) {
+                return Err(StubError::PermissionDenied(key));
+            }
+            self.store.lock().unwrap().insert(key, value);
+            Ok(())
+        }
+
+        fn reset_property(&self, channel: &str, property: &str, _recursive: bool) {
+            self.store
+                .lock()
+                .unwrap()
+                .remove(&format!("{channel}{property}"));
+        }
+    }
+
+    /// A private `dbus-daemon`, killed when dropped.
+    struct PrivateBus {
+        daemon: Child,
+        dir: PathBuf,
+        address: String,
+    }
+
+    impl PrivateBus {
+        /// Starts the daemon, or returns `None` if `dbus-daemon` is not installed.
+        fn start(name: &str) -> Option<PrivateBus> {
+            let dir = std::env::temp_dir().join(format!(
+                "xfce4-transparent-whiskermenu-{}-{name}",
+                std::process::id()
+            ));
+            fs::create_dir_all(&dir).unwrap();
+            let config = dir.join("bus.conf");
+            fs::write(&config, BUS_CONFIG).unwrap();
+            let mut daemon = match Command::new("dbus-daemon")
+                .arg(format!("--config-file={}", config.display()))
+                .args(["--print-address=1", "--nofork"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+            {
+                Ok(daemon) => daemon,
+                Err(e) => {
+                    eprintln!("skipping: cannot start dbus-daemon: {e}");
+                    fs::remove_dir_all(&dir).unwrap();
+                    return None;
+                }
+            };
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take().unwrap())
+                .read_line(&mut address)
+                .unwrap();
+            Some(PrivateBus {
+                daemon,
+                dir,
+                address: address.trim().to_string(),
+            })
+        }
+
+        fn connection(&self) -> Connection {
+            connection::Builder::address(self.address.as_str())
+                .unwrap()
+                .method_timeout(Duration::from_secs(5))
+                .build()
+                .unwrap()
+        }
+
+        /// Registers a stub xfconfd and connects an [`XfconfBus`] to it.
+        fn xfconf(&self, store: &Store, read_only: Option<&str>) -> (Connection, XfconfBus) {
+            let stub = StubXfconf {
+                store: store.clone(),
+                read_only: read_only.map(str::to_string),
+            };
+            let service = connection::Builder::address(self.address.as_str())
+                .unwrap()
+                .method_timeout(Duration::from_secs(5))
+                .serve_at(XFCONF_PATH, stub)
+                .unwrap()
+                .name(XFCONF_SERVICE)
+                .unwrap()
+                .build()
+                .unwrap();
+            (
+                service,
+                XfconfBus::with_connection(self.connection()).unwrap(),
+            )
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn rgba(values: [f64; 4]) -> XfconfValue {
+        XfconfValue::Array(values.into_iter().map(XfconfValue::Double).collect())
+    }
+
+    fn change(property: &str, previous: Option<XfconfValue>, value: XfconfValue) -> PropertyChange {
+        PropertyChange {
+            channel: "xfce4-panel".to_string(),
+            property: property.to_string(),
+            previous,
+            value,
+        }
+    }
+
+    /// Puts wanted values into the store the way a client would.
+    fn seed(bus: &XfconfBus, values: &[(&str, XfconfValue)]) {
+        for (property, value) in values {
+            bus.set_property("xfce4-panel", property, value).unwrap();
+        }
+    }
+
+    fn live(bus: &XfconfBus, property: &str) -> Option<XfconfValue> {
+        bus.get_property("xfce4-panel", property).unwrap()
+    }
+
+    #[test]
+    fn requires_xfconfd_on_the_bus() {
+        let Some(bus) = PrivateBus::start("missing") else {
+            return;
+        };
+        let error = XfconfBus::with_connection(bus.connection())
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("not available"), "{error}");
+    }
+
+    #[test]
+    fn gets_sets_and_resets_properties() {
+        let Some(bus) = PrivateBus::start("roundtrip") else {
+            return;
+        };
+        let store = Store::default();
+        let (_service, xfconf) = bus.xfconf(&store, None);
+
+        assert_eq!(live(&xfconf, "/panels/panel-1/size"), None);
+        let values = [
+            ("/panels/panel-1/size", XfconfValue::Uint(30)),
+            ("/panels/panel-1/position-locked", XfconfValue::Bool(true)),
+            (
+                "/panels/panel-1/output-name",
+                XfconfValue::String("eDP-1".into()),
+            ),
+            (
+                "/panels/panel-1/background-rgba",
+                rgba([0.25, 0.5, 1.0, 0.8]),
+            ),
+        ];
+        seed(&xfconf, &values);
+        for (property, value) in &values {
+            assert_eq!(live(&xfconf, property).as_ref(), Some(value));
+        }
+
+        xfconf
+            .reset_property("xfce4-panel", "/panels/panel-1/size")
+            .unwrap();
+        assert_eq!(live(&xfconf, "/panels/panel-1/size"), None);
+        assert_eq!(store.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn compares_with_the_live_values() {
+        let Some(bus) = PrivateBus::start("pending") else {
+            return;
+        };
+        let store = Store::default();
+        let (_service, xfconf) = bus.xfconf(&store, None);
+        let wanted = rgba([0.1, 0.1, 0.1, 0.7]);
+        let other = rgba([0.0, 0.0, 0.0, 1.0]);
+        seed(
+            &xfconf,
+            &[
+                ("/panels/panel-1/background-rgba", wanted.clone()),
+                ("/panels/panel-2/background-rgba", other.clone()),
+            ],
+        );
+
+        let changes = xfconf
+            .pending_changes(
+                "xfce4-panel",
+                &[
+                    ("/panels/panel-1/background-rgba".into(), wanted.clone()),
+                    ("/panels/panel-2/background-rgba".into(), wanted.clone()),
+                    ("/panels/panel-3/background-rgba".into(), wanted.clone()),
+                ],
+            )
+            .unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                change(
+                    "/panels/panel-2/background-rgba",
+                    Some(other),
+                    wanted.clone()
+                ),
+                change("/panels/panel-3/background-rgba", None, wanted),
+            ]
+        );
+    }
+
+    #[test]
+    fn applies_and_reverts_changes() {
+        let Some(bus) = PrivateBus::start("revert") else {
+            return;
+        };
+        let store = Store::default();
+        let (_service, xfconf) = bus.xfconf(&store, None);
+        let before = rgba([0.0, 0.0, 0.0, 1.0]);
+        let after = rgba([0.1, 0.1, 0.1, 0.7]);
+        seed(
+            &xfconf,
+            &[("/panels/panel-1/background-rgba", before.clone())],
+        );
+        let changes = [
+            change(
+                "/panels/panel-1/background-rgba",
+                Some(before.clone()),
+                after.clone(),
+            ),
+            change(
+                "/panels/panel-1/background-style",
+                None,
+                XfconfValue::Uint(1),
+            ),
+        ];
+
+        xfconf.apply(&changes).unwrap();
+        assert_eq!(
+            live(&xfconf, "/panels/panel-1/background-rgba"),
+            Some(after)
+        );
+        assert_eq!(
+            live(&xfconf, "/panels/panel-1/background-style"),
+            Some(XfconfValue::Uint(1))
+        );
+
+        assert!(xfconf.revert(&changes).is_empty());
+        assert_eq!(
+            live(&xfconf, "/panels/panel-1/background-rgba"),
+            Some(before)
+        );
+        assert_eq!(live(&xfconf, "/panels/panel-1/background-style"), None);
+    }
+
+    #[test]
+    fn rolls_back_when_a_property_cannot_be_set() {
+        let Some(bus) = PrivateBus::start("rollback") else {
+            return;
+        };
+        let store = Store::default();
+        let (_service, xfconf) =
+            bus.xfconf(&store, Some("xfce4-panel/panels/panel-2/background-rgba"));
+        let before = rgba([0.0, 0.0, 0.0, 1.0]);
+        let after = rgba([0.1, 0.1, 0.1, 0.7]);
+        seed(
+            &xfconf,
+            &[("/panels/panel-1/background-rgba", before.clone())],
+        );
+
+        let error = xfconf
+            .apply(&[
+                change(
+                    "/panels/panel-1/background-rgba",
+                    Some(before.clone()),
+                    after.clone(),
+                ),
+                change(
+                    "/panels/panel-1/background-style",
+                    None,
+                    XfconfValue::Uint(1),
+                ),
+                change("/panels/panel-2/background-rgba", None, after),
+            ])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("panel-2/background-rgba"), "{error}");
+        assert!(error.contains("rolled back"), "{error}");
+        assert_eq!(
+            live(&xfconf, "/panels/panel-1/background-rgba"),
+            Some(before)
+        );
+        assert_eq!(live(&xfconf, "/panels/panel-1/background-style"), None);
+        assert_eq!(live(&xfconf, "/panels/panel-2/background-rgba"), None);
+    }
+
+    #[test]
+    fn property_changes_survive_the_manifest() {
+        let changes = vec![
+            change(
+                "/panels/panel-1/background-rgba",
+                None,
+                rgba([0.1, 0.2, 0.3, 0.4]),
+            ),
+            change(
+                "/panels/panel-1/background-style",
+                Some(XfconfValue::Uint(0)),
+                XfconfValue::Uint(1),
+            ),
+        ];
+        #[derive(Serialize, Deserialize)]
+        struct Manifest {
+            xfconf: Vec<PropertyChange>,
+        }
+        let text = toml::to_string(&Manifest {
+            xfconf: changes.clone(),
+        })
+        .unwrap();
+        assert_eq!(toml::from_str::<Manifest>(&text).unwrap().xfconf, changes);
+    }
+
+    #[test]
+    fn describes_property_changes() {
+        assert_eq!(
+            change(
+                "/panels/panel-1/background-rgba",
+                Some(rgba([0.0, 0.0, 0.0, 1.0])),
+                rgba([0.1, 0.2, 0.3, 0.8]),
+            )
+            .to_string(),
+            "xfce4-panel/panels/panel-1/background-rgba: [0, 0, 0, 1] -> [0.1, 0.2, 0.3, 0.8]"
+        );
+        assert_eq!(
+            change(
+                "/panels/panel-1/background-style",
+                None,
+                XfconfValue::Uint(1)
+            )
+            .to_string(),
+            "xfce4-panel/panels/panel-1/background-style: (unset) -> 1"
+        );
+    }
+}